@@ -0,0 +1,398 @@
+use crate::engine::*;
+use glam::{Mat4, Vec3};
+use std::{path::PathBuf, process::ExitCode};
+
+const USAGE: &str = "\
+usage: surfaco [script.rhai [other.rhai] [mesh.obj | mesh.stl | mesh.ply]...]
+       surfaco stats script.rhai
+       surfaco contours script.rhai > contours.obj
+       surfaco intersect script.rhai (other.rhai | nx ny nz offset) > curves.obj
+       surfaco record script.rhai (out.gif | out.png) [--frames 60] [--fps 30]
+               [--size 500x500] [--scale 2] [--camera yaw,pitch,distance]
+               [--turntable] [--animate] [--rulers] [--with other.rhai | mesh.obj]...
+       surfaco export script.rhai (out.gltf | out.glb) [--frames 1] [--fps 30]
+       surfaco export script.rhai out.ply [--ascii]
+       surfaco export script.rhai out.svg [--size 500x500] [--camera yaw,pitch,distance]
+       surfaco sample script.rhai [--format csv|npy] > samples.csv";
+
+/// Loads and evaluates the script given as the only argument.
+fn geometry(args: &[String]) -> Result<Geometry, String> {
+	let [path] = args else {
+		return Err(USAGE.into());
+	};
+	let mut script = Script::load(path).map_err(|e| format!("{path}: {e}"))?;
+	Geometry::evaluate(&mut script).map_err(|e| format!("{path}: {e}"))
+}
+
+/// `surfaco stats script.rhai`, prints the measurements of the surface as JSON, in the
+/// space the script's `matrix()` maps to.
+pub fn stats(args: &[String]) -> ExitCode {
+	match geometry(args) {
+		Ok(geometry) => {
+			println!("{}", Stats::new(&geometry).to_json());
+			ExitCode::SUCCESS
+		}
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// `surfaco contours script.rhai`, prints the level sets of the surface as OBJ
+/// polylines, transformed by the script's `matrix()`.
+pub fn contours(args: &[String]) -> ExitCode {
+	let [path] = args else {
+		eprintln!("{USAGE}");
+		return ExitCode::FAILURE;
+	};
+	let result = Script::load(path).and_then(|mut script| {
+		let geometry = Geometry::evaluate(&mut script)?;
+		Ok((geometry, script.levels()))
+	});
+	match result {
+		Ok((geometry, levels)) => {
+			let polylines: Vec<_> = geometry
+				.contours(&levels)
+				.into_iter()
+				.map(|(level, points)| {
+					let points = points.into_iter().map(|p| geometry.transform.transform_point3(p));
+					(format!("level_{level}"), points.collect())
+				})
+				.collect();
+			match write_obj(&mut std::io::stdout().lock(), &polylines) {
+				Ok(()) => ExitCode::SUCCESS,
+				Err(e) => {
+					eprintln!("{e}");
+					ExitCode::FAILURE
+				}
+			}
+		}
+		Err(e) => {
+			eprintln!("{path}: {e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// `surfaco sample script.rhai --format npy`, prints u, v, x, y, z, r, g, b of every
+/// sample of the surface that isn't hidden as CSV or as a NumPy array.
+pub fn sample(args: &[String]) -> ExitCode {
+	let result = match args {
+		[path] => Ok((path, "csv")),
+		[path, option, format] | [option, format, path] if option == "--format" => Ok((path, format.as_str())),
+		_ => Err(USAGE.to_string()),
+	}
+	.and_then(|(path, format)| {
+		let npy = match format {
+			"csv" => false,
+			"npy" => true,
+			_ => return Err(format!("unknown format {format}, expected csv or npy")),
+		};
+		let geometry = geometry(std::slice::from_ref(path))?;
+		let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+		let written = if npy { write_npy(&mut out, &geometry) } else { write_csv(&mut out, &geometry) };
+		written.and_then(|()| std::io::Write::flush(&mut out)).map_err(|e| e.to_string())
+	});
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// `surfaco intersect script.rhai other.rhai` or `surfaco intersect script.rhai nx ny nz
+/// offset`, prints the intersection curves with another surface or with the plane
+/// `n . p = offset` as OBJ polylines, in the space the scripts' `matrix()` map to.
+pub fn intersect(args: &[String]) -> ExitCode {
+	let result = match args {
+		[path, other] => geometry(std::slice::from_ref(path)).and_then(|geometry| {
+			let other = self::geometry(std::slice::from_ref(other))?;
+			let relative = geometry.transform.inverse() * other.transform;
+			Ok((geometry.intersection(&other, relative), geometry.transform))
+		}),
+		[path, plane @ ..] if plane.len() == 4 => {
+			let plane: Result<Vec<f32>, _> = plane.iter().map(|x| x.parse::<f32>()).collect();
+			match plane {
+				Ok(plane) => match Vec3::new(plane[0], plane[1], plane[2]).try_normalize() {
+					Some(normal) => geometry(std::slice::from_ref(path)).map(|geometry| {
+						let plane = Plane { normal, offset: plane[3] }.local(geometry.transform);
+						(geometry.plane_intersection(plane), geometry.transform)
+					}),
+					None => Err("the normal of the plane is zero".into()),
+				},
+				Err(e) => Err(format!("plane: {e}")),
+			}
+		}
+		_ => Err(USAGE.into()),
+	};
+	match result {
+		Ok((curves, transform)) => {
+			let polylines: Vec<_> = curves
+				.into_iter()
+				.enumerate()
+				.map(|(k, curve)| {
+					let points = curve.into_iter().map(|p| transform.transform_point3(p));
+					(format!("intersection_{k}"), points.collect())
+				})
+				.collect();
+			match write_obj(&mut std::io::stdout().lock(), &polylines) {
+				Ok(()) => ExitCode::SUCCESS,
+				Err(e) => {
+					eprintln!("{e}");
+					ExitCode::FAILURE
+				}
+			}
+		}
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// `WIDTHxHEIGHT` in pixels.
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+	let (width, height) = value.split_once('x').ok_or("expected WIDTHxHEIGHT")?;
+	Ok((width.parse().map_err(|e| format!("{e}"))?, height.parse().map_err(|e| format!("{e}"))?))
+}
+
+/// `yaw,pitch,distance` of the camera.
+fn parse_camera(value: &str) -> Result<(f32, f32, f32), String> {
+	let camera: Vec<f32> = value.split(',').map(str::parse).collect::<Result<_, _>>().map_err(|e| format!("{e}"))?;
+	let [yaw, pitch, distance] = camera[..] else {
+		return Err("expected yaw,pitch,distance".into());
+	};
+	Ok((yaw, pitch, distance))
+}
+
+/// Yaw, pitch and distance looking down a bit at `yaw` from where the whole surface is in
+/// view.
+fn default_camera(geometry: &Geometry, yaw: f32) -> (f32, f32, f32) {
+	(yaw, 0.4, geometry.size() / 2.0 / (std::f32::consts::FRAC_PI_8).sin())
+}
+
+/// Options of `surfaco record`.
+struct Recording {
+	path: String,
+	other: Option<String>,
+	/// OBJ, STL or PLY files shown along with the surfaces.
+	overlays: Vec<PathBuf>,
+	output: PathBuf,
+	frames: u32,
+	fps: f32,
+	size: (u32, u32),
+	scale: u32,
+	/// Yaw, pitch and distance, `default_camera` if not given.
+	camera: Option<(f32, f32, f32)>,
+	turntable: bool,
+	animate: bool,
+	rulers: bool,
+}
+
+impl Recording {
+	fn parse(args: &[String]) -> Result<Self, String> {
+		let mut positional = Vec::new();
+		let mut recording = Self {
+			path: String::new(),
+			other: None,
+			overlays: Vec::new(),
+			output: PathBuf::new(),
+			frames: 60,
+			fps: 30.0,
+			size: (500, 500),
+			scale: 2,
+			camera: None,
+			turntable: false,
+			animate: false,
+			rulers: false,
+		};
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+			let invalid = |e: &dyn std::fmt::Display| format!("{arg}: {e}");
+			match arg.as_str() {
+				"--frames" => recording.frames = value()?.parse().map_err(|e| invalid(&e))?,
+				"--fps" => recording.fps = value()?.parse().map_err(|e| invalid(&e))?,
+				"--scale" => recording.scale = value()?.parse().map_err(|e| invalid(&e))?,
+				"--size" => recording.size = parse_size(value()?).map_err(|e| invalid(&e))?,
+				"--camera" => recording.camera = Some(parse_camera(value()?).map_err(|e| invalid(&e))?),
+				"--turntable" => recording.turntable = true,
+				"--animate" => recording.animate = true,
+				"--rulers" => recording.rulers = true,
+				"--with" => {
+					let path = value()?;
+					if is_mesh_path(path.as_ref()) {
+						recording.overlays.push(path.into());
+					} else {
+						recording.other = Some(path.clone());
+					}
+				}
+				_ if arg.starts_with("--") => return Err(format!("unknown option {arg}\n{USAGE}")),
+				_ => positional.push(arg.clone()),
+			}
+		}
+		let [path, output] = <[String; 2]>::try_from(positional).map_err(|_| USAGE.to_string())?;
+		if recording.frames == 0 || recording.fps <= 0.0 || recording.size.0 == 0 || recording.size.1 == 0 {
+			return Err("frames, fps and size must be positive".into());
+		}
+		if !recording.turntable && !recording.animate {
+			recording.turntable = true;
+		}
+		recording.path = path;
+		recording.output = output.into();
+		Ok(recording)
+	}
+
+	/// Where frame `k` goes when writing PNGs, `out.png` becomes `out_0000.png` and so on.
+	fn frame_path(&self, k: u32) -> PathBuf {
+		let stem = self.output.file_stem().unwrap_or_default().to_string_lossy();
+		self.output.with_file_name(format!("{stem}_{k:04}.png"))
+	}
+}
+
+/// `surfaco record script.rhai out.gif`, renders frames a fixed time step apart without
+/// a window, turning the surface once around the z axis over the recording and/or
+/// evaluating the scripts with `time` set to the time of each frame. Writes an animated
+/// GIF, or numbered PNGs next to the given one.
+pub fn record(args: &[String]) -> ExitCode {
+	let result = Recording::parse(args).and_then(|recording| {
+		let gif = match recording.output.extension().and_then(|e| e.to_str()) {
+			Some("gif") => true,
+			Some("png") => false,
+			_ => return Err(format!("{}: expected a .gif or .png output", recording.output.display())),
+		};
+		for path in std::iter::once(&recording.path).chain(&recording.other) {
+			geometry(std::slice::from_ref(path))?;
+		}
+		for path in &recording.overlays {
+			read_mesh(path).map_err(|e| format!("{}: {e}", path.display()))?;
+		}
+
+		let (width, height) = recording.size;
+		let canvas = Canvas::headless(width, height)?;
+		// Frames are rendered `scale` times larger, they have to fit in a texture too.
+		let largest = canvas.device.limits().max_texture_dimension_2d;
+		if width.max(height).saturating_mul(recording.scale.max(1)) > largest {
+			return Err(format!(
+				"{width}x{height} at scale {} is more than the {largest} pixels on a side the graphics device allows",
+				recording.scale
+			));
+		}
+		let mut writer = if gif {
+			Some(Gif::create(&recording.output, width, height, recording.fps).map_err(|e| e.to_string())?)
+		} else {
+			None
+		};
+		let mut state = crate::State::with_canvas(canvas, recording.path.clone().into(), recording.other.clone().map(PathBuf::from), &recording.overlays);
+		state.show = false;
+		state.camera_transform = recording.camera.unwrap_or_else(|| default_camera(&state.surface.geometry, state.camera_transform.0));
+		if recording.rulers {
+			state.surface.toggle_rulers(&state.canvas, &state.camera);
+		}
+		for k in 0..recording.frames {
+			let time = k as f32 / recording.fps;
+			if recording.animate {
+				state.set_time(time)?;
+			}
+			let turn = if recording.turntable {
+				std::f32::consts::TAU * k as f32 / recording.frames as f32
+			} else {
+				0.0
+			};
+			state.surface.mesh.transform = Mat4::from_rotation_z(turn) * state.surface.geometry.transform;
+			state.sync();
+			let mut pixels = state.capture(recording.scale);
+			match &mut writer {
+				Some(writer) => writer.push(&mut pixels).map_err(|e| e.to_string())?,
+				None => {
+					let path = recording.frame_path(k);
+					write_png(&path, width, height, &pixels).map_err(|e| format!("{}: {e}", path.display()))?;
+				}
+			}
+		}
+		Ok(())
+	});
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// `surfaco export script.rhai out.glb`, writes the surface to a file in the format its
+/// extension names. With `--frames`, the script is evaluated that many times `1 / fps`
+/// seconds apart and the frames exported as a glTF animation. PLY files are binary
+/// unless `--ascii` and include the curvatures. SVG files hold the parameter lines,
+/// contours and silhouette the camera sees.
+pub fn export(args: &[String]) -> ExitCode {
+	let result = (|| {
+		let mut positional = Vec::new();
+		let (mut frames, mut fps, mut ascii) = (1u32, 30.0f32, false);
+		let (mut size, mut camera) = ((500, 500), None);
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+			match arg.as_str() {
+				"--frames" => frames = value()?.parse().map_err(|e| format!("{arg}: {e}"))?,
+				"--fps" => fps = value()?.parse().map_err(|e| format!("{arg}: {e}"))?,
+				"--ascii" => ascii = true,
+				"--size" => size = parse_size(value()?).map_err(|e| format!("{arg}: {e}"))?,
+				"--camera" => camera = Some(parse_camera(value()?).map_err(|e| format!("{arg}: {e}"))?),
+				_ if arg.starts_with("--") => return Err(format!("unknown option {arg}\n{USAGE}")),
+				_ => positional.push(arg),
+			}
+		}
+		let [path, output] = positional[..] else {
+			return Err(USAGE.to_string());
+		};
+		if frames == 0 || fps <= 0.0 || size.0 == 0 || size.1 == 0 {
+			return Err("frames, fps and size must be positive".into());
+		}
+		let format = match std::path::Path::new(output).extension().and_then(|e| e.to_str()) {
+			Some(format @ ("gltf" | "glb" | "ply" | "svg")) => format,
+			_ => return Err(format!("{output}: unknown format, expected .gltf, .glb, .ply or .svg")),
+		};
+		if frames > 1 && matches!(format, "ply" | "svg") {
+			return Err("only glTF holds animations".into());
+		}
+
+		let mut script = Script::load(path).map_err(|e| format!("{path}: {e}"))?;
+		let geometries = (0..frames)
+			.map(|k| {
+				let time = k as f32 / fps;
+				script.set_time(time);
+				Geometry::evaluate(&mut script).map(|geometry| (time, geometry))
+			})
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|e| format!("{path}: {e}"))?;
+		let frames: Vec<_> = geometries.iter().map(|(time, geometry)| (*time, geometry)).collect();
+
+		let mut file = std::io::BufWriter::new(std::fs::File::create(output).map_err(|e| format!("{output}: {e}"))?);
+		match format {
+			"ply" => {
+				let curvatures = frames[0].1.curvature(&mut script);
+				write_ply(&mut file, frames[0].1, Some(&curvatures), !ascii)
+			}
+			"svg" => {
+				let geometry = frames[0].1;
+				let (yaw, pitch, distance) = camera.unwrap_or_else(|| default_camera(geometry, 1.0));
+				let view = (Camera::look(yaw, pitch, distance), Camera::projection(size.0 as f32 / size.1 as f32));
+				line_drawing(geometry, geometry.transform, view, size, Some(&script.levels())).write_svg(&mut file)
+			}
+			_ => write_gltf(&mut file, &frames, format == "glb"),
+		}
+		.map_err(|e| format!("{output}: {e}"))
+	})();
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
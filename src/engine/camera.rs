@@ -8,16 +8,14 @@ pub struct Camera {
 	view_buf: wgpu::Buffer,
 	pub(super) layout: wgpu::BindGroupLayout,
 	pub(super) group: wgpu::BindGroup,
+	/// Copies of the matrices on the GPU, for picking.
+	pub proj: Mat4,
+	pub view: Mat4,
 }
 
 impl Camera {
 	pub fn new(canvas: &Canvas) -> Self {
-		let proj_mat = Mat4::perspective_rh(
-			std::f32::consts::FRAC_PI_4,
-			canvas.config.width as f32 / canvas.config.height as f32,
-			0.1,
-			100.0,
-		);
+		let proj_mat = Self::projection(canvas.config.width as f32 / canvas.config.height as f32);
 		let view_mat = Mat4::look_at_rh(Vec3::X * 10.0, Vec3::ZERO, Vec3::Z);
 
 		let usage = wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
@@ -83,29 +81,58 @@ impl Camera {
 			view_buf,
 			layout,
 			group,
+			proj: proj_mat,
+			view: view_mat,
 		}
 	}
 
-	pub fn resize(&self, canvas: &Canvas) {
-		let proj_mat = Mat4::perspective_rh(
-			std::f32::consts::FRAC_PI_4,
-			canvas.config.width as f32 / canvas.config.height as f32,
-			0.1,
-			100.0,
-		);
+	pub fn resize(&mut self, canvas: &Canvas) {
+		let proj_mat = Self::projection(canvas.config.width as f32 / canvas.config.height as f32);
 		canvas
 			.queue
 			.write_buffer(&self.proj_buf, 0, bytemuck::cast_slice(&[proj_mat]));
+		self.proj = proj_mat;
 	}
 
-	pub fn set_transform(&self, queue: &wgpu::Queue, yaw: f32, pitch: f32, distance: f32) {
+	pub fn set_transform(&mut self, queue: &wgpu::Queue, yaw: f32, pitch: f32, distance: f32) {
+		let view_mat = Self::look(yaw, pitch, distance);
+		queue.write_buffer(&self.view_buf, 0, bytemuck::cast_slice(&[view_mat]));
+		self.view = view_mat;
+	}
+
+	/// Projection for a viewport `aspect` times as wide as it is high.
+	pub fn projection(aspect: f32) -> Mat4 {
+		Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, aspect, 0.1, 100.0)
+	}
+
+	/// View from `distance` away looking at the origin, turned `yaw` around the z axis and
+	/// `pitch` above the xy plane.
+	pub fn look(yaw: f32, pitch: f32, distance: f32) -> Mat4 {
 		let (sin_y, cos_y) = yaw.sin_cos();
 		let (sin_p, cos_p) = pitch.sin_cos();
-		let view_mat = Mat4::look_at_rh(
+		Mat4::look_at_rh(
 			vec3(cos_p * cos_y, cos_p * sin_y, sin_p).normalize_or_zero() * distance,
 			Vec3::ZERO,
 			Vec3::Z,
-		);
-		queue.write_buffer(&self.view_buf, 0, bytemuck::cast_slice(&[view_mat]));
+		)
+	}
+
+	/// Where a point in world space shows on a viewport of `size` pixels, from its top left
+	/// corner, or `None` behind the camera.
+	pub fn project(&self, point: Vec3, size: Vec2) -> Option<Vec2> {
+		let clip = self.proj * self.view * point.extend(1.0);
+		(clip.w > 0.0).then(|| {
+			let ndc = clip.xy() / clip.w;
+			vec2(ndc.x + 1.0, 1.0 - ndc.y) * size / 2.0
+		})
+	}
+
+	/// Ray through a point of the screen given in normalized device coordinates, as an
+	/// origin on the near plane and a unit direction, in world space.
+	pub fn ray(&self, ndc: Vec2) -> (Vec3, Vec3) {
+		let inverse = (self.proj * self.view).inverse();
+		let near = inverse.project_point3(ndc.extend(0.0));
+		let far = inverse.project_point3(ndc.extend(1.0));
+		(near, (far - near).normalize())
 	}
 }
@@ -19,9 +19,11 @@ pub fn depth(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureVie
 	texture.create_view(&Default::default())
 }
 
+/// The device and what is drawn to, the window or nothing when rendering offscreen. The
+/// configuration holds the size and format either way.
 pub struct Canvas {
-	pub window: Window,
-	pub surface: wgpu::Surface,
+	pub window: Option<Window>,
+	pub surface: Option<wgpu::Surface>,
 	pub device: wgpu::Device,
 	pub queue: wgpu::Queue,
 	pub config: wgpu::SurfaceConfiguration,
@@ -77,8 +79,8 @@ impl Canvas {
 		let depth_view = depth(&device, config.width, config.height);
 
 		Self {
-			window,
-			surface,
+			window: Some(window),
+			surface: Some(surface),
 			device,
 			queue,
 			config,
@@ -86,13 +88,62 @@ impl Canvas {
 		}
 	}
 
+	/// A canvas without a window, for drawing into a `Target` of the given size. Fails
+	/// without a graphics adapter or if the size is more than it allows.
+	pub fn headless(width: u32, height: u32) -> Result<Self, String> {
+		let instance = wgpu::Instance::default();
+		let (device, queue) = block_on(async {
+			let adapter = instance
+				.request_adapter(&wgpu::RequestAdapterOptions::default())
+				.await
+				.ok_or("no graphics adapter")?;
+			adapter
+				.request_device(
+					&wgpu::DeviceDescriptor {
+						label: Some("Canvas::Device"),
+						limits: wgpu::Limits::downlevel_defaults(),
+						..Default::default()
+					},
+					None,
+				)
+				.await
+				.map_err(|e| e.to_string())
+		})?;
+		let largest = device.limits().max_texture_dimension_2d;
+		if width.max(height) > largest {
+			return Err(format!("{width}x{height} is more than the {largest} pixels on a side the graphics device allows"));
+		}
+
+		let config = wgpu::SurfaceConfiguration {
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+			present_mode: wgpu::PresentMode::Fifo,
+			format: wgpu::TextureFormat::Rgba8UnormSrgb,
+			alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+			width,
+			height,
+			view_formats: vec![],
+		};
+		let depth_view = depth(&device, width, height);
+
+		Ok(Self {
+			window: None,
+			surface: None,
+			device,
+			queue,
+			config,
+			depth_view,
+		})
+	}
+
 	pub fn resize(&mut self, PhysicalSize { width, height }: PhysicalSize<u32>) {
 		if width == 0 || height == 0 {
 			return;
 		}
 		self.config.width = width;
 		self.config.height = height;
-		self.surface.configure(&self.device, &self.config);
+		if let Some(surface) = &self.surface {
+			surface.configure(&self.device, &self.config);
+		}
 		self.depth_view = depth(&self.device, width, height);
 	}
 }
@@ -0,0 +1,143 @@
+use super::{depth, Canvas};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Color and depth textures to render into instead of the window, in its format so the
+/// same pipelines draw to both.
+pub struct Target {
+	texture: wgpu::Texture,
+	pub view: wgpu::TextureView,
+	pub depth_view: wgpu::TextureView,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Target {
+	pub fn new(canvas: &Canvas, width: u32, height: u32) -> Self {
+		let texture = canvas.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("capture texture"),
+			size: wgpu::Extent3d {
+				width,
+				height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: canvas.config.format,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+			view_formats: &[],
+		});
+		Self {
+			view: texture.create_view(&Default::default()),
+			depth_view: depth(&canvas.device, width, height),
+			texture,
+			width,
+			height,
+		}
+	}
+
+	/// Copies what was drawn back from the GPU as opaque RGBA rows from the top.
+	pub fn read(&self, canvas: &Canvas) -> Vec<u8> {
+		let row = self.width * 4;
+		let padded = row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+		let buffer = canvas.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("capture buffer"),
+			size: (padded * self.height) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+		let mut encoder = canvas.device.create_command_encoder(&Default::default());
+		encoder.copy_texture_to_buffer(
+			self.texture.as_image_copy(),
+			wgpu::ImageCopyBuffer {
+				buffer: &buffer,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: std::num::NonZeroU32::new(padded),
+					rows_per_image: None,
+				},
+			},
+			self.texture.size(),
+		);
+		canvas.queue.submit(Some(encoder.finish()));
+
+		let slice = buffer.slice(..);
+		slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+		canvas.device.poll(wgpu::Maintain::Wait);
+		let bgra = matches!(
+			canvas.config.format,
+			wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+		);
+		let mut pixels = Vec::with_capacity((row * self.height) as usize);
+		for line in slice.get_mapped_range().chunks(padded as usize) {
+			for pixel in line[..row as usize].chunks_exact(4) {
+				let [r, g, b, _] = if bgra { [pixel[2], pixel[1], pixel[0], pixel[3]] } else { [pixel[0], pixel[1], pixel[2], pixel[3]] };
+				// The translucent mesh leaves the alpha channel of the window meaningless.
+				pixels.extend([r, g, b, 255]);
+			}
+		}
+		pixels
+	}
+}
+
+/// Averages each block of `factor` by `factor` RGBA pixels into one.
+pub fn downsample(pixels: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+	let (width, height, factor) = (width as usize, height as usize, factor as usize);
+	let mut out = Vec::with_capacity(width / factor * height / factor * 4);
+	for y in 0..height / factor {
+		for x in 0..width / factor {
+			let mut sum = [0u32; 4];
+			for (dx, dy) in itertools::iproduct!(0..factor, 0..factor) {
+				let i = ((y * factor + dy) * width + x * factor + dx) * 4;
+				for (s, &p) in sum.iter_mut().zip(&pixels[i..i + 4]) {
+					*s += p as u32;
+				}
+			}
+			let n = (factor * factor) as u32;
+			out.extend(sum.map(|s| ((s + n / 2) / n) as u8));
+		}
+	}
+	out
+}
+
+pub fn write_png<P: AsRef<Path>>(path: P, width: u32, height: u32, pixels: &[u8]) -> Result<(), png::EncodingError> {
+	let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.write_header()?.write_image_data(pixels)
+}
+
+/// Animated GIF that loops forever, written a frame at a time.
+pub struct Gif {
+	encoder: gif::Encoder<BufWriter<File>>,
+	width: u16,
+	height: u16,
+	/// Time each frame is shown, in hundredths of a second.
+	delay: u16,
+}
+
+impl Gif {
+	/// Fails without creating the file if either side is longer than the 65535 pixels a
+	/// GIF allows.
+	pub fn create<P: AsRef<Path>>(path: P, width: u32, height: u32, fps: f32) -> Result<Self, gif::EncodingError> {
+		let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+			let message = format!("a GIF can't be {width} by {height} pixels, at most {} on each side", u16::MAX);
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, message).into());
+		};
+		let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width, height, &[])?;
+		encoder.set_repeat(gif::Repeat::Infinite)?;
+		Ok(Self {
+			encoder,
+			width,
+			height,
+			delay: (100.0 / fps).round().max(2.0) as u16,
+		})
+	}
+
+	/// Adds a frame of RGBA rows, reduced to its own palette of 256 colors.
+	pub fn push(&mut self, pixels: &mut [u8]) -> Result<(), gif::EncodingError> {
+		let mut frame = gif::Frame::from_rgba_speed(self.width, self.height, pixels, 10);
+		frame.delay = self.delay;
+		self.encoder.write_frame(&frame)
+	}
+}
@@ -0,0 +1,123 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::Write;
+
+/// Which level sets get extracted.
+#[derive(Clone, Debug)]
+pub enum Levels {
+	/// That many levels evenly spaced strictly between the smallest and largest value.
+	Count(usize),
+	/// The given values.
+	Values(Vec<f32>),
+}
+
+impl Levels {
+	pub fn resolve(&self, values: &[f32]) -> Vec<f32> {
+		match self {
+			Self::Values(levels) => levels.clone(),
+			Self::Count(count) => {
+				let (min, max) = values
+					.iter()
+					.filter(|x| x.is_finite())
+					.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &x| (min.min(x), max.max(x)));
+				if min >= max {
+					return Vec::new();
+				}
+				(1..=*count)
+					.map(|k| min + (max - min) * k as f32 / (*count + 1) as f32)
+					.collect()
+			}
+		}
+	}
+}
+
+/// Extracts the level sets of `values` over a triangle mesh, the triangle counterpart of
+/// marching squares so it works the same on uniform grids, adaptive meshes and the other
+/// domains. Segments sharing a crossing are chained, so each level comes out as a few
+/// polylines, closed ones repeating their first point at the end.
+pub fn contours(
+	positions: &[Vec3],
+	values: &[f32],
+	indices: &[u32],
+	levels: &[f32],
+) -> Vec<(f32, Vec<Vec3>)> {
+	let mut polylines = Vec::new();
+	for &level in levels {
+		// Crossings are identified by the edge they lie on.
+		let mut segments = Vec::new();
+		let mut points = HashMap::new();
+		for face in indices.chunks_exact(3) {
+			let mut crossings = Vec::with_capacity(2);
+			for k in 0..3 {
+				let (a, b) = (face[k], face[(k + 1) % 3]);
+				let (fa, fb) = (values[a as usize], values[b as usize]);
+				// Vertices exactly on the level count as above it, so each edge is crossed once.
+				if (fa >= level) != (fb >= level) {
+					let edge = (a.min(b), a.max(b));
+					points.entry(edge).or_insert_with(|| {
+						let t = (level - fa) / (fb - fa);
+						positions[a as usize].lerp(positions[b as usize], t)
+					});
+					crossings.push(edge);
+				}
+			}
+			if let [p, q] = crossings[..] {
+				segments.push((p, q));
+			}
+		}
+		for line in chain(&segments) {
+			polylines.push((level, line.iter().map(|key| points[key]).collect()));
+		}
+	}
+	polylines
+}
+
+/// Joins segments whose endpoints have the same key into polylines, closed ones repeat
+/// their first key at the end.
+pub fn chain<K: Copy + Eq + Ord + Hash>(segments: &[(K, K)]) -> Vec<Vec<K>> {
+	let mut next: HashMap<K, Vec<K>> = HashMap::new();
+	for &(p, q) in segments {
+		next.entry(p).or_default().push(q);
+		next.entry(q).or_default().push(p);
+	}
+
+	// Start from the open ends first so those lines aren't split in two.
+	let mut starts: Vec<_> = next.keys().copied().collect();
+	starts.sort_unstable_by_key(|key| (next[key].len() != 1, *key));
+	let mut lines = Vec::new();
+	for start in starts {
+		if next[&start].is_empty() {
+			continue;
+		}
+		let mut line = vec![start];
+		let mut current = start;
+		while let Some(following) = next.get_mut(&current).and_then(Vec::pop) {
+			let back = next.get_mut(&following).unwrap();
+			let position = back.iter().position(|&key| key == current).unwrap();
+			back.swap_remove(position);
+			line.push(following);
+			current = following;
+		}
+		lines.push(line);
+	}
+	lines
+}
+
+/// Writes named polylines as a Wavefront OBJ file, one object of `l` elements each.
+pub fn write_obj(w: &mut impl Write, polylines: &[(String, Vec<Vec3>)]) -> std::io::Result<()> {
+	let mut first = 1;
+	for (name, points) in polylines {
+		writeln!(w, "o {name}")?;
+		for p in points {
+			writeln!(w, "v {} {} {}", p.x, p.y, p.z)?;
+		}
+		write!(w, "l")?;
+		for k in first..first + points.len() {
+			write!(w, " {k}")?;
+		}
+		writeln!(w)?;
+		first += points.len();
+	}
+	Ok(())
+}
@@ -0,0 +1,95 @@
+use glam::{vec2, Vec2, Vec3};
+
+/// Partial derivatives of a parametrization at a point, estimated with central
+/// differences of step `h`.
+#[derive(Clone, Copy, Debug)]
+pub struct Derivatives {
+	pub ru: Vec3,
+	pub rv: Vec3,
+	pub ruu: Vec3,
+	pub ruv: Vec3,
+	pub rvv: Vec3,
+}
+
+/// Curvatures of a surface at a point, `k1` is the largest principal curvature.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Curvature {
+	pub gaussian: f32,
+	pub mean: f32,
+	pub k1: f32,
+	pub k2: f32,
+}
+
+impl Derivatives {
+	/// Evaluates `f` on the 3x3 stencil around `uv`, `f` returns `None` where the surface
+	/// is not defined.
+	pub fn new(mut f: impl FnMut(Vec2) -> Option<Vec3>, uv: Vec2, h: f32) -> Option<Self> {
+		let mut at = |i: f32, j: f32| f(uv + vec2(i, j) * h);
+		let r = at(0.0, 0.0)?;
+		let (u0, u1) = (at(-1.0, 0.0)?, at(1.0, 0.0)?);
+		let (v0, v1) = (at(0.0, -1.0)?, at(0.0, 1.0)?);
+		let (d00, d01) = (at(-1.0, -1.0)?, at(-1.0, 1.0)?);
+		let (d10, d11) = (at(1.0, -1.0)?, at(1.0, 1.0)?);
+
+		Some(Self {
+			ru: (u1 - u0) / (2.0 * h),
+			rv: (v1 - v0) / (2.0 * h),
+			ruu: (u1 - 2.0 * r + u0) / (h * h),
+			ruv: (d11 - d10 - d01 + d00) / (4.0 * h * h),
+			rvv: (v1 - 2.0 * r + v0) / (h * h),
+		})
+	}
+
+	/// Unit normal, `ru x rv` normalized.
+	pub fn normal(&self) -> Vec3 {
+		self.ru.cross(self.rv).normalize_or_zero()
+	}
+
+	/// Coefficients `(E, F, G)` of the first fundamental form.
+	pub fn metric(&self) -> (f32, f32, f32) {
+		(self.ru.dot(self.ru), self.ru.dot(self.rv), self.rv.dot(self.rv))
+	}
+
+	/// Components `(a, b)` of the tangent vector closest to `vector`, so that
+	/// `a * ru + b * rv` is its projection on the tangent plane.
+	pub fn tangent_components(&self, vector: Vec3) -> Option<Vec2> {
+		let (e, f, g) = self.metric();
+		let det = e * g - f * f;
+		if det <= f32::EPSILON {
+			return None;
+		}
+		let (a, b) = (vector.dot(self.ru), vector.dot(self.rv));
+		Some(vec2(g * a - f * b, e * b - f * a) / det)
+	}
+
+	/// Second derivative of `(u, v)` along the geodesic going through this point with the
+	/// given velocity, that is `-Γ(velocity, velocity)` with the Christoffel symbols
+	/// taken from the tangential part of the second derivatives.
+	pub fn geodesic_acceleration(&self, velocity: Vec2) -> Option<Vec2> {
+		let (du, dv) = (velocity.x, velocity.y);
+		let second = self.ruu * du * du + 2.0 * self.ruv * du * dv + self.rvv * dv * dv;
+		self.tangent_components(-second)
+	}
+
+	/// Curvatures from the first and second fundamental forms, `None` at singular points.
+	pub fn curvature(&self) -> Option<Curvature> {
+		let (e, f, g) = self.metric();
+		let n = self.normal();
+		let (l, m, nn) = (self.ruu.dot(n), self.ruv.dot(n), self.rvv.dot(n));
+
+		let det = e * g - f * f;
+		if det <= f32::EPSILON {
+			return None;
+		}
+		let gaussian = (l * nn - m * m) / det;
+		let mean = (e * nn - 2.0 * f * m + g * l) / (2.0 * det);
+		let root = (mean * mean - gaussian).max(0.0).sqrt();
+		let curvature = Curvature {
+			gaussian,
+			mean,
+			k1: mean + root,
+			k2: mean - root,
+		};
+		[gaussian, mean].iter().all(|k| k.is_finite()).then_some(curvature)
+	}
+}
@@ -0,0 +1,151 @@
+use glam::{vec2, Vec2};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+/// Shape of the region of the `(u, v)` plane that gets sampled.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Domain {
+	/// The rectangle `[u_min, u_max] x [v_min, v_max]`.
+	#[default]
+	Rect,
+	/// Disk or annulus around the origin, sampled along `u_segments` rings and
+	/// `v_segments` sectors.
+	Disk { r_min: f32, r_max: f32 },
+	/// Triangle with the given corners, each side split into `u_segments` pieces.
+	Triangle([Vec2; 3]),
+	/// The part of the rectangle where the script's `in_domain(u, v)` holds.
+	Predicate,
+}
+
+/// Points and triangles of a disk or an annulus. A disk gets a single center vertex
+/// instead of a collapsed ring.
+pub fn disk(r_min: f32, r_max: f32, rings: usize, sectors: usize) -> (Vec<Vec2>, Vec<u32>) {
+	let rings = rings.max(1);
+	let sectors = sectors.max(3);
+	let mut points = Vec::new();
+	let mut indices = Vec::new();
+
+	let first = if r_min > 0.0 { 0 } else { 1 };
+	if first == 1 {
+		points.push(Vec2::ZERO);
+	}
+	for k in first..=rings {
+		let r = r_min + (r_max - r_min) * k as f32 / rings as f32;
+		for j in 0..sectors {
+			let (sin, cos) = (TAU * j as f32 / sectors as f32).sin_cos();
+			points.push(vec2(r * cos, r * sin));
+		}
+	}
+
+	let ring = |k: usize, j: usize| (first + (k - first) * sectors + j % sectors) as u32;
+	if first == 1 {
+		for j in 0..sectors {
+			indices.extend([0, ring(1, j + 1), ring(1, j)]);
+		}
+	}
+	for (k, j) in itertools::iproduct!(first..rings, 0..sectors) {
+		let (idx0, idx1) = (ring(k, j), ring(k, j + 1));
+		let (idx2, idx3) = (ring(k + 1, j + 1), ring(k + 1, j));
+		indices.extend([idx0, idx1, idx2, idx2, idx3, idx0]);
+	}
+
+	(points, indices)
+}
+
+/// Points and triangles of a triangle whose sides are split into `segments` pieces.
+pub fn triangle([a, b, c]: [Vec2; 3], segments: usize) -> (Vec<Vec2>, Vec<u32>) {
+	let n = segments.max(1);
+	let mut points = Vec::new();
+	let mut rows = Vec::new();
+	for i in 0..=n {
+		rows.push(points.len() as u32);
+		for j in 0..=n - i {
+			points.push(a + (b - a) * (i as f32 / n as f32) + (c - a) * (j as f32 / n as f32));
+		}
+	}
+
+	let mut indices = Vec::new();
+	for i in 0..n {
+		for j in 0..n - i {
+			let idx0 = rows[i] + j as u32;
+			let idx1 = idx0 + 1;
+			let idx3 = rows[i + 1] + j as u32;
+			indices.extend([idx0, idx1, idx3]);
+			if j + 1 < n - i {
+				indices.extend([idx1, idx3 + 1, idx3]);
+			}
+		}
+	}
+
+	(points, indices)
+}
+
+/// Points and triangles of the part of the grid over `[u_min, u_max] x [v_min, v_max]`
+/// where `in_domain` holds. Cells crossed by the border are cut where the predicate
+/// changes, found by bisection along their edges, so the mesh follows the border
+/// instead of a staircase.
+pub fn predicate(
+	(u_min, u_max, v_min, v_max): (f32, f32, f32, f32),
+	(u_segments, v_segments): (usize, usize),
+	mut in_domain: impl FnMut(f32, f32) -> bool,
+) -> (Vec<Vec2>, Vec<u32>) {
+	let du = (u_max - u_min) / u_segments as f32;
+	let dv = (v_max - v_min) / v_segments as f32;
+	let at = |i: usize, j: usize| vec2(u_min + i as f32 * du, v_min + j as f32 * dv);
+
+	let mut inside = HashMap::new();
+	for (i, j) in itertools::iproduct!(0..=u_segments, 0..=v_segments) {
+		let p = at(i, j);
+		inside.insert((i, j), in_domain(p.x, p.y));
+	}
+
+	let mut points = Vec::new();
+	let mut corners = HashMap::new();
+	let mut crossings = HashMap::new();
+	let mut indices = Vec::new();
+	let mut polygon = Vec::new();
+	for (i, j) in itertools::iproduct!(0..u_segments, 0..v_segments) {
+		// Same orientation as the uniform grid.
+		let ring = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)];
+		let flags = ring.map(|key| inside[&key]);
+		if !flags.contains(&true) {
+			continue;
+		}
+
+		polygon.clear();
+		for k in 0..4 {
+			let (a, b) = (ring[k], ring[(k + 1) % 4]);
+			if flags[k] {
+				polygon.push(*corners.entry(a).or_insert_with(|| {
+					points.push(at(a.0, a.1));
+					points.len() as u32 - 1
+				}));
+			}
+			if flags[k] != flags[(k + 1) % 4] {
+				let edge = (a.min(b), a.max(b));
+				polygon.push(*crossings.entry(edge).or_insert_with(|| {
+					let (mut pin, mut pout) = (at(a.0, a.1), at(b.0, b.1));
+					if flags[(k + 1) % 4] {
+						std::mem::swap(&mut pin, &mut pout);
+					}
+					for _ in 0..12 {
+						let mid = (pin + pout) / 2.0;
+						if in_domain(mid.x, mid.y) {
+							pin = mid;
+						} else {
+							pout = mid;
+						}
+					}
+					points.push(pin);
+					points.len() as u32 - 1
+				}));
+			}
+		}
+
+		for k in 1..polygon.len() - 1 {
+			indices.extend([polygon[0], polygon[k], polygon[k + 1]]);
+		}
+	}
+
+	(points, indices)
+}
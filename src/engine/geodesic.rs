@@ -0,0 +1,99 @@
+use super::Derivatives;
+use glam::{Vec2, Vec3};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Integrates the geodesic equation with RK4 from `start` with initial `velocity` in
+/// parameter space, for at most `length` of arc length in steps of `ds`. `f` is the
+/// parametrization, `wrap` brings points back into the domain across periodic
+/// parameters and returns `None` outside of it, `h` is the finite difference step.
+/// Returns the points of the curve on the surface, it stops early where the surface
+/// is singular or the curve leaves the domain.
+pub fn geodesic(
+	mut f: impl FnMut(Vec2) -> Option<Vec3>,
+	wrap: impl Fn(Vec2) -> Option<Vec2>,
+	start: Vec2,
+	velocity: Vec2,
+	(h, ds, length): (f32, f32, f32),
+) -> Vec<Vec3> {
+	let derivatives = |f: &mut dyn FnMut(Vec2) -> Option<Vec3>, uv: Vec2| Derivatives::new(f, wrap(uv)?, h);
+	let mut points = Vec::new();
+	let Some(d) = derivatives(&mut f, start) else {
+		return points;
+	};
+	// Unit speed, so time is arc length.
+	let speed = (d.ru * velocity.x + d.rv * velocity.y).length();
+	if speed <= f32::EPSILON {
+		return points;
+	}
+	let (mut uv, mut velocity) = (start, velocity / speed);
+	let Some(p) = f(uv) else { return points };
+	points.push(p);
+
+	let rhs = |f: &mut dyn FnMut(Vec2) -> Option<Vec3>, uv: Vec2, velocity: Vec2| {
+		let acceleration = derivatives(f, uv)?.geodesic_acceleration(velocity)?;
+		Some((velocity, acceleration))
+	};
+	for _ in 0..(length / ds) as usize {
+		let Some((k1, l1)) = rhs(&mut f, uv, velocity) else { break };
+		let Some((k2, l2)) = rhs(&mut f, uv + k1 * ds / 2.0, velocity + l1 * ds / 2.0) else { break };
+		let Some((k3, l3)) = rhs(&mut f, uv + k2 * ds / 2.0, velocity + l2 * ds / 2.0) else { break };
+		let Some((k4, l4)) = rhs(&mut f, uv + k3 * ds, velocity + l3 * ds) else { break };
+		let Some(next) = wrap(uv + (k1 + 2.0 * k2 + 2.0 * k3 + k4) * ds / 6.0) else { break };
+		let Some(p) = f(next) else { break };
+		uv = next;
+		velocity += (l1 + 2.0 * l2 + 2.0 * l3 + l4) * ds / 6.0;
+		points.push(p);
+	}
+	points
+}
+
+/// Shortest path from vertex `from` to vertex `to` along the edges of the mesh, found
+/// with Dijkstra's algorithm. Returns the vertices of the path in order, `None` if
+/// they aren't connected.
+pub fn shortest_path(positions: &[Vec3], indices: &[u32], from: u32, to: u32) -> Option<Vec<u32>> {
+	let mut neighbours = vec![Vec::new(); positions.len()];
+	for face in indices.chunks_exact(3) {
+		for k in 0..3 {
+			let (a, b) = (face[k] as usize, face[(k + 1) % 3] as usize);
+			neighbours[a].push(b);
+			neighbours[b].push(a);
+		}
+	}
+
+	let mut distance = vec![f32::INFINITY; positions.len()];
+	let mut previous = vec![usize::MAX; positions.len()];
+	let mut queue = BinaryHeap::new();
+	distance[from as usize] = 0.0;
+	queue.push(Reverse((0u32, from as usize)));
+	while let Some(Reverse((d, a))) = queue.pop() {
+		let d = f32::from_bits(d);
+		if a == to as usize {
+			break;
+		}
+		if d > distance[a] {
+			continue;
+		}
+		for &b in &neighbours[a] {
+			let next = d + positions[a].distance(positions[b]);
+			if next < distance[b] {
+				distance[b] = next;
+				previous[b] = a;
+				// Non negative floats order the same as their bits.
+				queue.push(Reverse((next.to_bits(), b)));
+			}
+		}
+	}
+
+	if !distance[to as usize].is_finite() {
+		return None;
+	}
+	let mut path = vec![to];
+	let mut current = to as usize;
+	while current != from as usize {
+		current = previous[current];
+		path.push(current as u32);
+	}
+	path.reverse();
+	Some(path)
+}
@@ -0,0 +1,130 @@
+use super::{segment, Camera, Canvas, LineVertex, Lines};
+use glam::Vec3;
+
+/// Smallest of 1, 2 and 5 times a power of ten that is at least `x`.
+pub fn nice_step(x: f32) -> f32 {
+	let power = 10f32.powf(x.max(f32::MIN_POSITIVE).log10().floor());
+	[1.0, 2.0, 5.0, 10.0]
+		.into_iter()
+		.map(|k| k * power)
+		.find(|&step| step >= x)
+		.unwrap_or(10.0 * power)
+}
+
+/// Axes from `-length` to `length` in red, green and blue, dimmer on the negative side,
+/// with a tick every `spacing`.
+pub fn axes(length: f32, spacing: f32) -> Vec<LineVertex> {
+	let mut lines = Vec::new();
+	let tick = spacing * 0.15;
+	let count = (length / spacing) as i32;
+	for (axis, across, color) in [
+		(Vec3::X, Vec3::Y, [1.0, 0.2, 0.2]),
+		(Vec3::Y, Vec3::X, [0.2, 1.0, 0.2]),
+		(Vec3::Z, Vec3::X, [0.3, 0.5, 1.0]),
+	] {
+		let dim = color.map(|c| c * 0.5);
+		segment(&mut lines, Vec3::ZERO, axis * length, color);
+		segment(&mut lines, Vec3::ZERO, -axis * length, dim);
+		for k in (-count..=count).filter(|&k| k != 0) {
+			let p = axis * k as f32 * spacing;
+			segment(&mut lines, p - across * tick, p + across * tick, if k > 0 { color } else { dim });
+		}
+	}
+	lines
+}
+
+/// Square grid in the `z = 0` plane, `count` lines on each side of the origin every
+/// `spacing`, every fifth one brighter.
+pub fn grid(spacing: f32, count: i32) -> Vec<LineVertex> {
+	let mut lines = Vec::new();
+	let extent = spacing * count as f32;
+	for k in -count..=count {
+		let color = if k % 5 == 0 { [0.55; 3] } else { [0.4; 3] };
+		let t = k as f32 * spacing;
+		segment(&mut lines, Vec3::new(t, -extent, 0.0), Vec3::new(t, extent, 0.0), color);
+		segment(&mut lines, Vec3::new(-extent, t, 0.0), Vec3::new(extent, t, 0.0), color);
+	}
+	lines
+}
+
+/// The twelve edges of the box with opposite corners `min` and `max`.
+pub fn bounding_box(min: Vec3, max: Vec3, color: [f32; 3]) -> Vec<LineVertex> {
+	let mut lines = Vec::new();
+	let corner = |i: usize| Vec3::select(glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+	for i in 0..8 {
+		for bit in [1, 2, 4] {
+			if i & bit == 0 {
+				segment(&mut lines, corner(i), corner(i | bit), color);
+			}
+		}
+	}
+	lines
+}
+
+/// World axes and ground grid, each toggled on its own. Their spacing follows the
+/// distance of the camera so there are always about ten grid lines in view.
+#[derive(Default)]
+pub struct Reference {
+	axes: Option<Lines>,
+	grid: Option<Lines>,
+	pub show_axes: bool,
+	pub show_grid: bool,
+	spacing: f32,
+}
+
+impl Reference {
+	/// Rebuilds what is shown if the spacing for a camera at `distance` changed.
+	pub fn update(&mut self, canvas: &Canvas, camera: &Camera, distance: f32) {
+		let spacing = nice_step(distance / 10.0);
+		if spacing != self.spacing {
+			self.spacing = spacing;
+			self.axes = None;
+			self.grid = None;
+		}
+		if self.show_axes && self.axes.is_none() {
+			self.axes = Some(Lines::new(canvas, camera, &axes(spacing * 20.0, spacing)));
+		}
+		if self.show_grid && self.grid.is_none() {
+			self.grid = Some(Lines::new(canvas, camera, &grid(spacing, 20)));
+		}
+	}
+
+	pub fn render<'r>(&'r self, render_pass: &mut wgpu::RenderPass<'r>, camera: &'r Camera) {
+		if let Some(axes) = self.axes.as_ref().filter(|_| self.show_axes) {
+			axes.render(render_pass, camera);
+		}
+		if let Some(grid) = self.grid.as_ref().filter(|_| self.show_grid) {
+			grid.render(render_pass, camera);
+		}
+	}
+}
+
+/// Axes along the three edges of the box from `min` to `max` that meet at `min`, with
+/// ticks pointing away from the box at round values. Also returns where to write the
+/// values of the ticks and the names of the axes.
+pub fn rulers(min: Vec3, max: Vec3, color: [f32; 3]) -> (Vec<LineVertex>, Vec<(Vec3, String)>) {
+	let mut lines = Vec::new();
+	let mut labels = Vec::new();
+	// No visible samples leave the box inverted.
+	let size = (max - min).max_element();
+	if size <= 0.0 {
+		return (lines, labels);
+	}
+	let tick = size * 0.03;
+	for (axis, out, name) in [(Vec3::X, -Vec3::Y, "x"), (Vec3::Y, -Vec3::X, "y"), (Vec3::Z, -Vec3::X, "z")] {
+		let (low, high) = (min.dot(axis), max.dot(axis));
+		let base = min - axis * low;
+		segment(&mut lines, min, base + axis * high, color);
+		// Flat sides still get a tick or two.
+		let step = nice_step((high - low).max(size * 0.01) / 5.0);
+		let decimals = (-step.log10().floor()).max(0.0) as usize;
+		for k in (low / step).ceil() as i32..=(high / step).floor() as i32 {
+			let value = k as f32 * step;
+			let p = base + axis * value;
+			segment(&mut lines, p, p + out * tick, color);
+			labels.push((p + out * tick * 3.0, format!("{value:.decimals$}")));
+		}
+		labels.push((base + axis * high + (axis + out) * tick * 3.0, name.to_string()));
+	}
+	(lines, labels)
+}
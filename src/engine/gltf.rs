@@ -0,0 +1,193 @@
+use super::Geometry;
+use base64::Engine;
+use glam::{Mat4, Vec3};
+use std::io::{self, Write};
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// The vertices a geometry's triangles use, in the space its `matrix()` maps to.
+struct Frame {
+	positions: Vec<Vec3>,
+	normals: Vec<Vec3>,
+	colors: Vec<[f32; 4]>,
+	uvs: Vec<[f32; 2]>,
+	indices: Vec<u32>,
+}
+
+impl Frame {
+	fn new(geometry: &Geometry) -> Self {
+		let (used, indices) = geometry.compact();
+		let normal_matrix = geometry.transform.inverse().transpose();
+		let vertices = || used.iter().map(|&index| &geometry.vertices[index as usize]);
+		Self {
+			positions: vertices().map(|v| geometry.transform.transform_point3(v.position.into())).collect(),
+			// glTF wants unit normals, degenerate points get one along z.
+			normals: vertices()
+				.map(|v| normal_matrix.transform_vector3(v.normal.into()).try_normalize().unwrap_or(Vec3::Z))
+				.collect(),
+			colors: vertices().map(|v| [v.color[0], v.color[1], v.color[2], v.alpha]).collect(),
+			uvs: used.iter().map(|&index| geometry.samples[index as usize].uv.into()).collect(),
+			indices,
+		}
+	}
+}
+
+/// Binary buffer with its views and accessors as JSON.
+#[derive(Default)]
+struct Buffer {
+	data: Vec<u8>,
+	views: Vec<String>,
+	accessors: Vec<String>,
+}
+
+impl Buffer {
+	/// Appends `data` in a view of its own with an accessor for `count` elements of type
+	/// `kind`, returns the index of the accessor.
+	fn push(&mut self, data: &[u8], component: u32, kind: &str, count: usize, target: Option<u32>, bounds: Option<(Vec<f32>, Vec<f32>)>) -> usize {
+		let target = target.map_or(String::new(), |t| format!(", \"target\": {t}"));
+		self.views.push(format!(
+			"{{\"buffer\": 0, \"byteOffset\": {}, \"byteLength\": {}{target}}}",
+			self.data.len(),
+			data.len()
+		));
+		self.data.extend_from_slice(data);
+		let bounds = bounds.map_or(String::new(), |(min, max)| format!(", \"min\": {min:?}, \"max\": {max:?}"));
+		self.accessors.push(format!(
+			"{{\"bufferView\": {}, \"componentType\": {component}, \"count\": {count}, \"type\": \"{kind}\"{bounds}}}",
+			self.views.len() - 1
+		));
+		self.accessors.len() - 1
+	}
+
+	fn vec3(&mut self, values: &[Vec3], bounded: bool) -> usize {
+		let bounds = bounded.then(|| {
+			let min = values.iter().fold(Vec3::splat(f32::INFINITY), |a, &b| a.min(b));
+			let max = values.iter().fold(Vec3::splat(f32::NEG_INFINITY), |a, &b| a.max(b));
+			(min.to_array().to_vec(), max.to_array().to_vec())
+		});
+		self.push(bytemuck::cast_slice(values), FLOAT, "VEC3", values.len(), Some(ARRAY_BUFFER), bounds)
+	}
+}
+
+/// Writes the surface as glTF 2.0, one `.glb` file if `binary` or JSON with the buffer
+/// embedded. The first frame is the mesh, the others become morph targets of it that an
+/// animation blends through at their times, so they must share its triangles. Positions
+/// are in the space the scripts' `matrix()` map to, turned so z stays up in y up viewers.
+pub fn write_gltf<W: Write>(w: &mut W, frames: &[(f32, &Geometry)], binary: bool) -> io::Result<()> {
+	let meshes: Vec<Frame> = frames.iter().map(|(_, geometry)| Frame::new(geometry)).collect();
+	let base = &meshes[0];
+	if base.indices.is_empty() {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "the surface has no triangles"));
+	}
+	if meshes[1..].iter().any(|m| m.indices != base.indices || m.positions.len() != base.positions.len()) {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"the triangles change over time, the frames can't be morph targets of each other",
+		));
+	}
+
+	let mut buffer = Buffer::default();
+	let position = buffer.vec3(&base.positions, true);
+	let normal = buffer.vec3(&base.normals, false);
+	let color = buffer.push(bytemuck::cast_slice(&base.colors), FLOAT, "VEC4", base.colors.len(), Some(ARRAY_BUFFER), None);
+	let uv = buffer.push(bytemuck::cast_slice(&base.uvs), FLOAT, "VEC2", base.uvs.len(), Some(ARRAY_BUFFER), None);
+	let indices = buffer.push(
+		bytemuck::cast_slice(&base.indices),
+		UNSIGNED_INT,
+		"SCALAR",
+		base.indices.len(),
+		Some(ELEMENT_ARRAY_BUFFER),
+		None,
+	);
+
+	let mut targets = Vec::new();
+	for mesh in &meshes[1..] {
+		let delta = |a: &[Vec3], b: &[Vec3]| a.iter().zip(b).map(|(a, b)| *a - *b).collect::<Vec<_>>();
+		let position = buffer.vec3(&delta(&mesh.positions, &base.positions), true);
+		let normal = buffer.vec3(&delta(&mesh.normals, &base.normals), false);
+		targets.push(format!("{{\"POSITION\": {position}, \"NORMAL\": {normal}}}"));
+	}
+
+	let mut animation = String::new();
+	if !targets.is_empty() {
+		let times: Vec<f32> = frames.iter().map(|(time, _)| *time).collect();
+		let bounds = (vec![times[0]], vec![times[times.len() - 1]]);
+		let input = buffer.push(bytemuck::cast_slice(&times), FLOAT, "SCALAR", times.len(), None, Some(bounds));
+		// Frame k is all of target k - 1 and nothing of the others.
+		let weights: Vec<f32> = itertools::iproduct!(0..frames.len(), 1..frames.len())
+			.map(|(frame, target)| if frame == target { 1.0 } else { 0.0 })
+			.collect();
+		let output = buffer.push(bytemuck::cast_slice(&weights), FLOAT, "SCALAR", weights.len(), None, None);
+		animation = format!(
+			concat!(
+				",\n  \"animations\": [{{\"channels\": [{{\"sampler\": 0, \"target\": {{\"node\": 0, \"path\": \"weights\"}}}}],",
+				" \"samplers\": [{{\"input\": {}, \"output\": {}, \"interpolation\": \"LINEAR\"}}]}}]",
+			),
+			input, output,
+		);
+	}
+
+	let opaque = base.colors.iter().all(|c| c[3] >= 1.0);
+	let uri = if binary {
+		String::new()
+	} else {
+		let data = base64::engine::general_purpose::STANDARD.encode(&buffer.data);
+		format!(", \"uri\": \"data:application/octet-stream;base64,{data}\"")
+	};
+	let matrix = Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2).to_cols_array();
+	let json = format!(
+		concat!(
+			"{{\n",
+			"  \"asset\": {{\"version\": \"2.0\", \"generator\": \"surfaco\"}},\n",
+			"  \"scene\": 0,\n",
+			"  \"scenes\": [{{\"nodes\": [0]}}],\n",
+			"  \"nodes\": [{{\"mesh\": 0, \"matrix\": {:?}}}],\n",
+			"  \"meshes\": [{{\"primitives\": [{{",
+			"\"attributes\": {{\"POSITION\": {}, \"NORMAL\": {}, \"COLOR_0\": {}, \"TEXCOORD_0\": {}}}, ",
+			"\"indices\": {}, \"material\": 0{}}}]{}}}],\n",
+			"  \"materials\": [{{\"pbrMetallicRoughness\": {{\"metallicFactor\": 0.0, \"roughnessFactor\": 1.0}}, ",
+			"\"doubleSided\": true, \"alphaMode\": \"{}\"}}],\n",
+			"  \"buffers\": [{{\"byteLength\": {}{}}}],\n",
+			"  \"bufferViews\": [\n    {}\n  ],\n",
+			"  \"accessors\": [\n    {}\n  ]{}\n",
+			"}}\n",
+		),
+		matrix,
+		position,
+		normal,
+		color,
+		uv,
+		indices,
+		if targets.is_empty() { String::new() } else { format!(", \"targets\": [{}]", targets.join(", ")) },
+		if targets.is_empty() { String::new() } else { format!(", \"weights\": {:?}", vec![0.0f32; targets.len()]) },
+		if opaque { "OPAQUE" } else { "BLEND" },
+		buffer.data.len(),
+		uri,
+		buffer.views.join(",\n    "),
+		buffer.accessors.join(",\n    "),
+		animation,
+	);
+
+	if !binary {
+		return w.write_all(json.as_bytes());
+	}
+	// Both chunks are padded to four bytes, the JSON with spaces.
+	let pad = |n: usize| (4 - n % 4) % 4;
+	let mut json = json.into_bytes();
+	json.resize(json.len() + pad(json.len()), b' ');
+	let mut data = buffer.data;
+	data.resize(data.len() + pad(data.len()), 0);
+	let length = 12 + 8 + json.len() + 8 + data.len();
+	w.write_all(b"glTF")?;
+	w.write_all(&2u32.to_le_bytes())?;
+	w.write_all(&(length as u32).to_le_bytes())?;
+	w.write_all(&(json.len() as u32).to_le_bytes())?;
+	w.write_all(b"JSON")?;
+	w.write_all(&json)?;
+	w.write_all(&(data.len() as u32).to_le_bytes())?;
+	w.write_all(b"BIN\0")?;
+	w.write_all(&data)
+}
@@ -0,0 +1,429 @@
+use super::{fill_normals, Vertex};
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+
+/// Color of the vertices of files that don't give one.
+const GRAY: [f32; 3] = [0.8, 0.8, 0.8];
+
+fn invalid(message: impl Into<String>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn vertex(position: [f32; 3], color: [f32; 3], alpha: f32) -> Vertex {
+	Vertex {
+		position,
+		color,
+		normal: [0.0; 3],
+		alpha,
+	}
+}
+
+/// Splits the polygon into a fan of triangles.
+fn fan(indices: &mut Vec<u32>, polygon: &[u32]) {
+	for k in 1..polygon.len().saturating_sub(1) {
+		indices.extend([polygon[0], polygon[k], polygon[k + 1]]);
+	}
+}
+
+/// Whether `read_mesh` reads files with the extension of `path`.
+pub fn is_mesh_path(path: &Path) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.is_some_and(|e| ["obj", "stl", "ply"].contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Reads the triangles of an OBJ, STL or PLY file, by its extension. Polygons become fans
+/// of triangles. Normals other than those of PLY vertices are computed from the faces,
+/// smooth where the file shares vertices between them and flat for STL. Colors are those
+/// of PLY vertices, or of OBJ vertices that have six numbers, taken as linear.
+pub fn read_mesh(path: &Path) -> io::Result<(Vec<Vertex>, Vec<u32>)> {
+	let mut file = io::BufReader::new(std::fs::File::open(path)?);
+	let (mut vertices, indices) = match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+		Some("obj") => read_obj(file)?,
+		Some("stl") => read_stl(&mut file)?,
+		Some("ply") => read_ply(file)?,
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown format, expected .obj, .stl or .ply")),
+	};
+	if indices.iter().any(|&index| index as usize >= vertices.len()) {
+		return Err(invalid("a face refers to a vertex that doesn't exist"));
+	}
+	if indices.is_empty() {
+		return Err(invalid("no faces"));
+	}
+	fill_normals(&mut vertices, &indices);
+	Ok((vertices, indices))
+}
+
+/// `v` and `f` lines, the rest is ignored.
+fn read_obj(reader: impl BufRead) -> io::Result<(Vec<Vertex>, Vec<u32>)> {
+	let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+	for (number, line) in reader.lines().enumerate() {
+		let line = line?;
+		let error = |e: &dyn std::fmt::Display| invalid(format!("line {}: {e}", number + 1));
+		let mut words = line.split_whitespace();
+		match words.next() {
+			Some("v") => {
+				let numbers: Vec<f32> = words.map(str::parse).collect::<Result<_, _>>().map_err(|e| error(&e))?;
+				match numbers[..] {
+					[x, y, z, r, g, b, ..] => vertices.push(vertex([x, y, z], [r, g, b], 1.0)),
+					[x, y, z, ..] => vertices.push(vertex([x, y, z], GRAY, 1.0)),
+					_ => return Err(error(&"expected x y z")),
+				}
+			}
+			Some("f") => {
+				// Corners are `v`, `v/vt`, `v//vn` or `v/vt/vn`, counted from 1 or back
+				// from the last vertex if negative.
+				let polygon = words
+					.map(|corner| {
+						let index: i64 = corner.split('/').next().unwrap_or_default().parse().map_err(|e| error(&e))?;
+						let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+						u32::try_from(index).map_err(|_| error(&"vertex index out of range"))
+					})
+					.collect::<io::Result<Vec<_>>>()?;
+				fan(&mut indices, &polygon);
+			}
+			_ => {}
+		}
+	}
+	Ok((vertices, indices))
+}
+
+/// Binary STL, or ASCII if it starts with `solid` and its size doesn't match the number
+/// of triangles it would have as binary, since some binary files start with `solid` too.
+fn read_stl(reader: &mut impl Read) -> io::Result<(Vec<Vertex>, Vec<u32>)> {
+	let mut data = Vec::new();
+	reader.read_to_end(&mut data)?;
+	let binary = data.len() >= 84 && {
+		let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+		data.len() == 84 + count * 50 || !data.starts_with(b"solid")
+	};
+
+	let mut vertices = Vec::new();
+	if binary {
+		let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+		let triangles = data[84..].chunks_exact(50).take(count);
+		if triangles.len() < count {
+			return Err(invalid("the file ends before its last triangle"));
+		}
+		for triangle in triangles {
+			// The facet normal comes first, it is computed again from the corners.
+			for corner in triangle[12..48].chunks_exact(12) {
+				let position = [0, 4, 8].map(|k| f32::from_le_bytes(corner[k..k + 4].try_into().unwrap()));
+				vertices.push(vertex(position, GRAY, 1.0));
+			}
+		}
+	} else {
+		let text = std::str::from_utf8(&data).map_err(|e| invalid(e.to_string()))?;
+		let mut words = text.split_whitespace();
+		while let Some(word) = words.next() {
+			if word == "vertex" {
+				let position: Vec<f32> = words.by_ref().take(3).map(str::parse::<f32>).collect::<Result<_, _>>().map_err(|e| invalid(e.to_string()))?;
+				let [x, y, z] = position[..] else {
+					return Err(invalid("expected x y z after vertex"));
+				};
+				vertices.push(vertex([x, y, z], GRAY, 1.0));
+			}
+		}
+		vertices.truncate(vertices.len() / 3 * 3);
+	}
+	let indices = (0..vertices.len() as u32).collect();
+	Ok((vertices, indices))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PlyFormat {
+	Ascii,
+	Little,
+	Big,
+}
+
+/// Type of a property or of the length or items of a list property.
+#[derive(Clone, Copy)]
+enum PlyType {
+	I8,
+	U8,
+	I16,
+	U16,
+	I32,
+	U32,
+	F32,
+	F64,
+}
+
+impl PlyType {
+	fn parse(name: &str) -> io::Result<Self> {
+		Ok(match name {
+			"char" | "int8" => Self::I8,
+			"uchar" | "uint8" => Self::U8,
+			"short" | "int16" => Self::I16,
+			"ushort" | "uint16" => Self::U16,
+			"int" | "int32" => Self::I32,
+			"uint" | "uint32" => Self::U32,
+			"float" | "float32" => Self::F32,
+			"double" | "float64" => Self::F64,
+			_ => return Err(invalid(format!("unknown property type {name}"))),
+		})
+	}
+
+	/// Colors stored as integers go from 0 to the largest value of their type.
+	fn color_scale(self) -> f64 {
+		match self {
+			Self::U8 | Self::I8 => 255.0,
+			Self::U16 | Self::I16 => 65535.0,
+			_ => 1.0,
+		}
+	}
+}
+
+struct PlyProperty {
+	name: String,
+	kind: PlyType,
+	/// Type of the length if it is a list.
+	list: Option<PlyType>,
+}
+
+struct PlyElement {
+	name: String,
+	count: usize,
+	properties: Vec<PlyProperty>,
+}
+
+/// Numbers of the body of a PLY file, one at a time whatever the format.
+struct PlyReader<R> {
+	reader: R,
+	format: PlyFormat,
+	/// Words left on the current line of an ASCII file.
+	words: std::vec::IntoIter<String>,
+}
+
+impl<R: BufRead> PlyReader<R> {
+	fn next(&mut self, kind: PlyType) -> io::Result<f64> {
+		if self.format == PlyFormat::Ascii {
+			loop {
+				if let Some(word) = self.words.next() {
+					return word.parse().map_err(|_| invalid(format!("expected a number, found {word}")));
+				}
+				let mut line = String::new();
+				if self.reader.read_line(&mut line)? == 0 {
+					return Err(invalid("the file ends before its last element"));
+				}
+				self.words = line.split_whitespace().map(String::from).collect::<Vec<_>>().into_iter();
+			}
+		}
+		let mut bytes = [0u8; 8];
+		let size = match kind {
+			PlyType::I8 | PlyType::U8 => 1,
+			PlyType::I16 | PlyType::U16 => 2,
+			PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+			PlyType::F64 => 8,
+		};
+		let bytes = &mut bytes[..size];
+		self.reader.read_exact(bytes)?;
+		if self.format == PlyFormat::Big {
+			bytes.reverse();
+		}
+		let bytes = &*bytes;
+		Ok(match kind {
+			PlyType::I8 => bytes[0] as i8 as f64,
+			PlyType::U8 => bytes[0] as f64,
+			PlyType::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+			PlyType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+			PlyType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+			PlyType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+			PlyType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+			PlyType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+		})
+	}
+}
+
+/// The `vertex` and `face` elements of an ASCII or binary PLY file, other elements are
+/// skipped.
+fn read_ply(mut reader: impl BufRead) -> io::Result<(Vec<Vertex>, Vec<u32>)> {
+	let mut line = String::new();
+	reader.read_line(&mut line)?;
+	if line.trim_end() != "ply" {
+		return Err(invalid("not a PLY file"));
+	}
+	let mut format = None;
+	let mut elements: Vec<PlyElement> = Vec::new();
+	loop {
+		line.clear();
+		if reader.read_line(&mut line)? == 0 {
+			return Err(invalid("the header has no end_header"));
+		}
+		let words: Vec<&str> = line.split_whitespace().collect();
+		match words[..] {
+			["format", name, _] => {
+				format = Some(match name {
+					"ascii" => PlyFormat::Ascii,
+					"binary_little_endian" => PlyFormat::Little,
+					"binary_big_endian" => PlyFormat::Big,
+					_ => return Err(invalid(format!("unknown format {name}"))),
+				})
+			}
+			["element", name, count] => elements.push(PlyElement {
+				name: name.into(),
+				count: count.parse().map_err(|_| invalid(format!("bad count of {name}")))?,
+				properties: Vec::new(),
+			}),
+			["property", "list", length, kind, name] => elements
+				.last_mut()
+				.ok_or_else(|| invalid("property before any element"))?
+				.properties
+				.push(PlyProperty {
+					name: name.into(),
+					kind: PlyType::parse(kind)?,
+					list: Some(PlyType::parse(length)?),
+				}),
+			["property", "list", ..] => return Err(invalid("expected property list <length type> <item type> <name>")),
+			["property", kind, name] => elements
+				.last_mut()
+				.ok_or_else(|| invalid("property before any element"))?
+				.properties
+				.push(PlyProperty {
+					name: name.into(),
+					kind: PlyType::parse(kind)?,
+					list: None,
+				}),
+			["end_header"] => break,
+			_ => {}
+		}
+	}
+	let mut reader = PlyReader {
+		reader,
+		format: format.ok_or_else(|| invalid("the header has no format"))?,
+		words: Vec::new().into_iter(),
+	};
+
+	let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+	let mut values = Vec::new();
+	for element in &elements {
+		for _ in 0..element.count {
+			values.clear();
+			let mut polygon = Vec::new();
+			for property in &element.properties {
+				match property.list {
+					Some(length) => {
+						let length = reader.next(length)? as usize;
+						let items = (0..length).map(|_| reader.next(property.kind)).collect::<io::Result<Vec<_>>>()?;
+						if element.name == "face" && matches!(property.name.as_str(), "vertex_indices" | "vertex_index") {
+							polygon = items
+								.into_iter()
+								.map(|index| {
+									if (0.0..=u32::MAX as f64).contains(&index) && index.fract() == 0.0 {
+										Ok(index as u32)
+									} else {
+										Err(invalid(format!("bad vertex index {index}")))
+									}
+								})
+								.collect::<io::Result<_>>()?;
+						}
+					}
+					None => values.push((property.name.as_str(), reader.next(property.kind)?, property.kind)),
+				}
+			}
+			match element.name.as_str() {
+				"vertex" => {
+					let get = |name: &str, default: f64| {
+						values
+							.iter()
+							.find(|(n, _, _)| *n == name)
+							.map_or(default, |(_, value, kind)| match name {
+								"red" | "green" | "blue" | "alpha" => value / kind.color_scale(),
+								_ => *value,
+							}) as f32
+					};
+					let [r, g, b] = GRAY;
+					vertices.push(Vertex {
+						position: [get("x", 0.0), get("y", 0.0), get("z", 0.0)],
+						color: [get("red", r as f64), get("green", g as f64), get("blue", b as f64)],
+						normal: [get("nx", 0.0), get("ny", 0.0), get("nz", 0.0)],
+						alpha: get("alpha", 1.0),
+					});
+				}
+				"face" => fan(&mut indices, &polygon),
+				_ => {}
+			}
+		}
+	}
+	Ok((vertices, indices))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{to_vertices, write_ply, Geometry, Sample};
+	use glam::{Mat4, Vec3};
+
+	#[test]
+	fn obj_negative_indices() {
+		let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf -4 -3 -2\nf 2/1 -1//1 3/1/1\n";
+		let (vertices, indices) = read_obj(obj.as_bytes()).unwrap();
+		assert_eq!(vertices.len(), 4);
+		assert_eq!(indices, [0, 1, 2, 1, 3, 2]);
+		assert!(read_obj("v 0 0 0\nf -2 1 1\n".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn stl_binary_or_ascii() {
+		// Binary, though its header starts with `solid` like an ASCII file.
+		let mut stl = b"solid but binary".to_vec();
+		stl.resize(80, 0);
+		stl.extend(1u32.to_le_bytes());
+		for x in [0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+			stl.extend(x.to_le_bytes());
+		}
+		stl.extend([0, 0]);
+		let (vertices, indices) = read_stl(&mut stl.as_slice()).unwrap();
+		assert_eq!(vertices.iter().map(|v| v.position).collect::<Vec<_>>(), [[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+		assert_eq!(indices, [0, 1, 2]);
+
+		let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 2 0 0\nvertex 0 2 0\nendloop\nendfacet\nendsolid t\n";
+		let (vertices, _) = read_stl(&mut stl.as_bytes()).unwrap();
+		assert_eq!(vertices.iter().map(|v| v.position).collect::<Vec<_>>(), [[0.0; 3], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]]);
+	}
+
+	#[test]
+	fn ply_round_trip() {
+		let samples: Vec<Sample> = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::new(1.0, 1.0, 0.5)]
+			.into_iter()
+			.map(|position| {
+				let mut sample = Sample::new(position);
+				sample.value = Some(position.x);
+				sample.scalars = vec![("x".into(), 7.0), ("red".into(), 2.0)];
+				sample
+			})
+			.collect();
+		let geometry = Geometry {
+			vertices: to_vertices(&samples),
+			samples,
+			indices: vec![0, 1, 2, 1, 3, 2],
+			transform: Mat4::IDENTITY,
+			bad_samples: 0,
+		};
+		for binary in [false, true] {
+			let mut ply = Vec::new();
+			write_ply(&mut ply, &geometry, None, binary).unwrap();
+			let (vertices, indices) = read_ply(ply.as_slice()).unwrap();
+			assert_eq!(indices, geometry.indices);
+			for (read, written) in vertices.iter().zip(&geometry.vertices) {
+				assert_eq!(read.position, written.position);
+				let color = written.color.map(|c| (c * 255.0).round() / 255.0);
+				assert_eq!(read.color, color);
+			}
+		}
+	}
+
+	#[test]
+	fn ply_bad_faces() {
+		let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\n";
+		let vertices = "0 0 0\n1 0 0\n0 1 0\n";
+		let ply = |list: &str, face: &str| format!("{header}{list}\nend_header\n{vertices}{face}\n");
+		let list = "property list uchar int vertex_indices";
+		assert!(read_ply(ply(list, "3 0 1 2").as_bytes()).is_ok());
+		assert!(read_ply(ply(list, "3 0 -1 2").as_bytes()).is_err());
+		assert!(read_ply(ply(list, "3 0 nan 2").as_bytes()).is_err());
+		assert!(read_ply(ply(list, "3 0 1.5 2").as_bytes()).is_err());
+		assert!(read_ply(ply("property list uchar vertex_indices", "3 0 1 2").as_bytes()).is_err());
+	}
+}
@@ -0,0 +1,216 @@
+use super::chain;
+use glam::{IVec3, Mat4, Quat, Vec3};
+use std::collections::HashMap;
+
+/// The plane of the points `p` with `normal.dot(p) == offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+	pub normal: Vec3,
+	pub offset: f32,
+}
+
+impl Default for Plane {
+	fn default() -> Self {
+		Self {
+			normal: Vec3::X,
+			offset: 0.0,
+		}
+	}
+}
+
+impl Plane {
+	/// Signed distance from the plane, positive on the side the normal points to.
+	pub fn distance(&self, p: Vec3) -> f32 {
+		self.normal.dot(p) - self.offset
+	}
+
+	/// The same plane in the coordinates `transform` maps to the ones this one is given
+	/// in. Its normal stays a unit vector only if `transform` keeps lengths.
+	pub fn local(&self, transform: Mat4) -> Self {
+		Self {
+			normal: transform.transpose().transform_vector3(self.normal),
+			offset: self.offset - self.normal.dot(transform.w_axis.truncate()),
+		}
+	}
+
+	/// Turns the normal by `yaw` around the z axis, then tilts it by `tilt` towards it.
+	pub fn rotate(&mut self, yaw: f32, tilt: f32) {
+		let normal = Quat::from_rotation_z(yaw) * self.normal;
+		let axis = Vec3::Z.cross(normal).try_normalize().unwrap_or(Vec3::X);
+		self.normal = (Quat::from_axis_angle(axis, -tilt) * normal).normalize();
+	}
+
+	/// Places the `[-1, 1]` square of the `z = 0` plane, as drawn by `Quad`, onto this
+	/// plane, scaled by `size`.
+	pub fn transform(&self, size: f32) -> Mat4 {
+		Mat4::from_scale_rotation_translation(
+			Vec3::splat(size),
+			Quat::from_rotation_arc(Vec3::Z, self.normal),
+			self.normal * self.offset,
+		)
+	}
+}
+
+/// Intersection curves of two triangle meshes, found triangle against triangle with a
+/// uniform grid to skip the pairs that are far apart. Segments are joined where their
+/// ends are within a small fraction of the size of the triangles, neighbouring pairs
+/// compute the same points a bit differently and both meshes may share edges.
+pub fn intersect(
+	(a_positions, a_indices): (&[Vec3], &[u32]),
+	(b_positions, b_indices): (&[Vec3], &[u32]),
+) -> Vec<Vec<Vec3>> {
+	let triangle = |positions: &[Vec3], indices: &[u32], face: usize| {
+		[0, 1, 2].map(|k| positions[indices[face * 3 + k] as usize])
+	};
+	let edges: f32 = b_indices
+		.chunks_exact(3)
+		.map(|face| b_positions[face[0] as usize].distance(b_positions[face[1] as usize]))
+		.sum();
+	let cell = (2.0 * edges / (b_indices.len() / 3).max(1) as f32).max(f32::EPSILON);
+	let bounds = |t: [Vec3; 3]| {
+		let min = t[0].min(t[1]).min(t[2]);
+		let max = t[0].max(t[1]).max(t[2]);
+		((min / cell).floor().as_ivec3(), (max / cell).floor().as_ivec3())
+	};
+	let cells = |(min, max): (IVec3, IVec3)| {
+		itertools::iproduct!(min.x..=max.x, min.y..=max.y, min.z..=max.z).map(|(x, y, z)| IVec3::new(x, y, z))
+	};
+
+	let mut grid: HashMap<IVec3, Vec<usize>> = HashMap::new();
+	for face in 0..b_indices.len() / 3 {
+		for key in cells(bounds(triangle(b_positions, b_indices, face))) {
+			grid.entry(key).or_default().push(face);
+		}
+	}
+
+	let tolerance = cell * 1e-4;
+	let snap_cell = |p: Vec3| (p / tolerance).floor().as_ivec3();
+	let mut snapped: HashMap<IVec3, Vec<u32>> = HashMap::new();
+	let mut points: Vec<Vec3> = Vec::new();
+	let mut snap = |p: Vec3| {
+		let key = snap_cell(p);
+		let near = itertools::iproduct!(-1..=1, -1..=1, -1..=1)
+			.filter_map(|(x, y, z)| snapped.get(&(key + IVec3::new(x, y, z))))
+			.flatten()
+			.find(|&&id| points[id as usize].distance(p) <= tolerance)
+			.copied();
+		near.unwrap_or_else(|| {
+			points.push(p);
+			snapped.entry(key).or_default().push(points.len() as u32 - 1);
+			points.len() as u32 - 1
+		})
+	};
+
+	let mut segments = Vec::new();
+	let mut candidates = Vec::new();
+	for a_face in 0..a_indices.len() / 3 {
+		let a = triangle(a_positions, a_indices, a_face);
+		candidates.clear();
+		candidates.extend(cells(bounds(a)).filter_map(|key| grid.get(&key)).flatten().copied());
+		candidates.sort_unstable();
+		candidates.dedup();
+		for &b_face in &candidates {
+			if let Some((p, q)) = triangle_triangle(a, triangle(b_positions, b_indices, b_face)) {
+				let (p, q) = (snap(p), snap(q));
+				if p != q {
+					segments.push((p.min(q), p.max(q)));
+				}
+			}
+		}
+	}
+	// Segments along an edge shared by two triangles come out twice.
+	segments.sort_unstable();
+	segments.dedup();
+
+	chain(&segments)
+		.into_iter()
+		.map(|line| line.iter().map(|&id| points[id as usize]).collect())
+		.collect()
+}
+
+/// Segment where two triangles cross, as the overlap of the segments where each one
+/// crosses the plane of the other, along the line both planes share. Coplanar
+/// triangles don't count.
+fn triangle_triangle(a: [Vec3; 3], b: [Vec3; 3]) -> Option<(Vec3, Vec3)> {
+	let normal = |t: [Vec3; 3]| (t[1] - t[0]).cross(t[2] - t[0]);
+	let (na, nb) = (normal(a), normal(b));
+	let direction = na.cross(nb);
+	if direction.length_squared() <= f32::EPSILON * na.length_squared() * nb.length_squared() {
+		return None;
+	}
+
+	// Extent of the part of `t` on the plane through `o`, as parameters along the line.
+	let interval = |t: [Vec3; 3], n: Vec3, o: Vec3| {
+		let distances = t.map(|p| n.dot(p - o));
+		let mut ends: Option<((f32, Vec3), (f32, Vec3))> = None;
+		for k in 0..3 {
+			let (p, q) = (t[k], t[(k + 1) % 3]);
+			let (dp, dq) = (distances[k], distances[(k + 1) % 3]);
+			let x = if dp == 0.0 {
+				p
+			} else if (dp < 0.0) != (dq < 0.0) && dq != 0.0 {
+				p.lerp(q, dp / (dp - dq))
+			} else {
+				continue;
+			};
+			let s = direction.dot(x);
+			ends = Some(match ends {
+				None => ((s, x), (s, x)),
+				Some((min, max)) => (if s < min.0 { (s, x) } else { min }, if s > max.0 { (s, x) } else { max }),
+			});
+		}
+		ends
+	};
+	let (a_min, a_max) = interval(a, nb, b[0])?;
+	let (b_min, b_max) = interval(b, na, a[0])?;
+	let start = if a_min.0 > b_min.0 { a_min } else { b_min };
+	let end = if a_max.0 < b_max.0 { a_max } else { b_max };
+	(start.0 < end.0).then_some((start.1, end.1))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{to_vertices, Geometry, Sample, SurfaceConfig};
+	use std::f32::consts::{PI, TAU};
+
+	/// Unit sphere around `center`.
+	fn sphere(center: Vec3) -> Geometry {
+		let config = SurfaceConfig::new(0.0, TAU, 0.0, PI, 48, 32);
+		let f = |u: f32, v: f32| Sample::new(center + Vec3::new(v.sin() * u.cos(), v.sin() * u.sin(), v.cos()));
+		let (samples, indices, _) = config.generate(f, |_, _| true);
+		Geometry {
+			vertices: to_vertices(&samples),
+			samples,
+			indices,
+			transform: Mat4::IDENTITY,
+			bad_samples: 0,
+		}
+	}
+
+	/// Checks that the curves are one circle around the x axis at `x` of `radius`, up
+	/// to how far the triangles are from the sphere.
+	fn assert_circle(curves: &[Vec<Vec3>], x: f32, radius: f32) {
+		assert_eq!(curves.len(), 1);
+		assert!(curves[0].len() > 20);
+		for p in &curves[0] {
+			assert!((p.x - x).abs() < 0.02, "{p}");
+			assert!((glam::vec2(p.y, p.z).length() - radius).abs() < 0.02, "{p}");
+		}
+	}
+
+	#[test]
+	fn plane_and_sphere() {
+		// Planes are given in the space `matrix()` maps to, here twice as large.
+		let mut sphere = sphere(Vec3::ZERO);
+		sphere.transform = Mat4::from_scale(Vec3::splat(2.0));
+		let plane = Plane { normal: Vec3::X, offset: 1.0 }.local(sphere.transform);
+		assert_circle(&sphere.plane_intersection(plane), 0.5, 0.75f32.sqrt());
+	}
+
+	#[test]
+	fn two_spheres() {
+		let (a, b) = (sphere(Vec3::ZERO), sphere(Vec3::X));
+		assert_circle(&a.intersection(&b, Mat4::IDENTITY), 0.5, 0.75f32.sqrt());
+	}
+}
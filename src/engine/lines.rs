@@ -0,0 +1,178 @@
+use super::{Camera, Canvas};
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+	pub position: [f32; 3],
+	pub color: [f32; 3],
+}
+
+/// Appends the segment from `a` to `b`.
+pub fn segment(lines: &mut Vec<LineVertex>, a: Vec3, b: Vec3, color: [f32; 3]) {
+	lines.push(LineVertex { position: a.into(), color });
+	lines.push(LineVertex { position: b.into(), color });
+}
+
+/// Appends an arrow from `from` to `from + vector` with a small head.
+pub fn arrow(lines: &mut Vec<LineVertex>, from: Vec3, vector: Vec3, color: [f32; 3]) {
+	let to = from + vector;
+	segment(lines, from, to, color);
+
+	let length = vector.length();
+	if length == 0.0 {
+		return;
+	}
+	let direction = vector / length;
+	let side = direction.any_orthonormal_vector() * length * 0.1;
+	let back = to - direction * length * 0.25;
+	segment(lines, to, back + side, color);
+	segment(lines, to, back - side, color);
+}
+
+/// Appends a polyline as a list of segments.
+pub fn polyline(lines: &mut Vec<LineVertex>, points: &[Vec3], color: [f32; 3]) {
+	for pair in points.windows(2) {
+		segment(lines, pair[0], pair[1], color);
+	}
+}
+
+/// A set of colored segments drawn with depth testing.
+pub struct Lines {
+	rp: wgpu::RenderPipeline,
+	vb: Option<wgpu::Buffer>,
+	tb: wgpu::Buffer,
+	tg: wgpu::BindGroup,
+	vlen: u32,
+	pub transform: Mat4,
+}
+
+impl Lines {
+	pub fn new(canvas: &Canvas, camera: &Camera, lines: &[LineVertex]) -> Self {
+		let tb = canvas
+			.device
+			.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("lines transform_buffer"),
+				usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+				contents: bytemuck::cast_slice(&[Mat4::IDENTITY]),
+			});
+
+		let transform_layout =
+			canvas
+				.device
+				.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+					label: Some("lines transform_layout"),
+					entries: &[wgpu::BindGroupLayoutEntry {
+						ty: wgpu::BindingType::Buffer {
+							ty: wgpu::BufferBindingType::Uniform,
+							has_dynamic_offset: false,
+							min_binding_size: None,
+						},
+						binding: 0,
+						visibility: wgpu::ShaderStages::VERTEX,
+						count: None,
+					}],
+				});
+
+		let tg = canvas.device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("lines transform_group"),
+			layout: &transform_layout,
+			entries: &[wgpu::BindGroupEntry {
+				binding: 0,
+				resource: tb.as_entire_binding(),
+			}],
+		});
+
+		let pipeline_layout =
+			canvas
+				.device
+				.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+					label: Some("lines pipeline_layout"),
+					bind_group_layouts: &[&camera.layout, &transform_layout],
+					push_constant_ranges: &[],
+				});
+
+		let shader = canvas
+			.device
+			.create_shader_module(wgpu::include_wgsl!("lines.wgsl"));
+
+		let rp = canvas
+			.device
+			.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("lines render_pipeline"),
+				layout: Some(&pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &shader,
+					entry_point: "vs_main",
+					buffers: &[wgpu::VertexBufferLayout {
+						step_mode: wgpu::VertexStepMode::Vertex,
+						array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+						attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+					}],
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &shader,
+					entry_point: "fs_main",
+					targets: &[Some(wgpu::ColorTargetState {
+						format: canvas.config.format,
+						blend: None,
+						write_mask: wgpu::ColorWrites::ALL,
+					})],
+				}),
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: wgpu::TextureFormat::Depth32Float,
+					depth_write_enabled: true,
+					depth_compare: wgpu::CompareFunction::Less,
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				primitive: wgpu::PrimitiveState {
+					topology: wgpu::PrimitiveTopology::LineList,
+					..Default::default()
+				},
+				multisample: wgpu::MultisampleState::default(),
+				multiview: None,
+			});
+
+		let mut this = Self {
+			rp,
+			vb: None,
+			tb,
+			tg,
+			vlen: 0,
+			transform: Mat4::IDENTITY,
+		};
+		this.set_lines(canvas, lines);
+		this
+	}
+
+	/// Replaces the segments, `lines` holds two vertices per segment.
+	pub fn set_lines(&mut self, canvas: &Canvas, lines: &[LineVertex]) {
+		self.vlen = lines.len() as u32;
+		self.vb = (!lines.is_empty()).then(|| {
+			canvas
+				.device
+				.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("lines vertex_buffer"),
+					usage: wgpu::BufferUsages::VERTEX,
+					contents: bytemuck::cast_slice(lines),
+				})
+		});
+	}
+
+	pub fn render<'r>(&'r self, render_pass: &mut wgpu::RenderPass<'r>, camera: &'r Camera) {
+		let Some(vb) = &self.vb else {
+			return;
+		};
+		render_pass.set_bind_group(0, &camera.group, &[]);
+		render_pass.set_bind_group(1, &self.tg, &[]);
+		render_pass.set_vertex_buffer(0, vb.slice(..));
+		render_pass.set_pipeline(&self.rp);
+		render_pass.draw(0..self.vlen, 0..1);
+	}
+
+	pub fn update_transform_buffer(&self, queue: &wgpu::Queue) {
+		queue.write_buffer(&self.tb, 0, bytemuck::cast_slice(&[self.transform]));
+	}
+}
@@ -1,5 +1,5 @@
-use super::{Camera, Canvas};
-use glam::Mat4;
+use super::{read_mesh, Camera, Canvas, Plane};
+use glam::{Mat4, Vec3};
 use wgpu::util::DeviceExt;
 
 #[repr(C)]
@@ -7,6 +7,55 @@ use wgpu::util::DeviceExt;
 pub struct Vertex {
 	pub position: [f32; 3],
 	pub color: [f32; 3],
+	pub normal: [f32; 3],
+	pub alpha: f32,
+}
+
+/// Fills every zero normal with the area weighted average of the adjacent face normals.
+pub fn fill_normals(vertices: &mut [Vertex], indices: &[u32]) {
+	let missing: Vec<bool> = vertices.iter().map(|v| v.normal == [0.0; 3]).collect();
+	if !missing.contains(&true) {
+		return;
+	}
+
+	let mut normals = vec![Vec3::ZERO; vertices.len()];
+	for face in indices.chunks_exact(3) {
+		let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(vertices[face[i] as usize].position));
+		let normal = (b - a).cross(c - a);
+		for &i in face {
+			normals[i as usize] += normal;
+		}
+	}
+
+	for ((vertex, normal), missing) in vertices.iter_mut().zip(normals).zip(missing) {
+		if missing {
+			vertex.normal = normal.normalize_or_zero().into();
+		}
+	}
+}
+
+/// First triangle hit by the ray from `origin` along `direction`, as its index and the
+/// distance along the ray.
+pub fn raycast(vertices: &[Vertex], indices: &[u32], origin: Vec3, direction: Vec3) -> Option<(usize, f32)> {
+	let mut hit: Option<(usize, f32)> = None;
+	for (face, triangle) in indices.chunks_exact(3).enumerate() {
+		// Möller–Trumbore, both sides count.
+		let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(vertices[triangle[i] as usize].position));
+		let (ab, ac) = (b - a, c - a);
+		let p = direction.cross(ac);
+		let det = ab.dot(p);
+		if det.abs() <= f32::EPSILON {
+			continue;
+		}
+		let s = (origin - a) / det;
+		let q = s.cross(ab);
+		let (u, v) = (s.dot(p), direction.dot(q));
+		let t = ac.dot(q);
+		if u >= 0.0 && v >= 0.0 && u + v <= 1.0 && t > 0.0 && hit.is_none_or(|(_, best)| t < best) {
+			hit = Some((face, t));
+		}
+	}
+	hit
 }
 
 pub struct Mesh {
@@ -15,6 +64,7 @@ pub struct Mesh {
 	vb: wgpu::Buffer,
 	ib: wgpu::Buffer,
 	tb: wgpu::Buffer,
+	cb: wgpu::Buffer,
 	tg: wgpu::BindGroup,
 	ilen: u32,
 	pub transform: Mat4,
@@ -24,22 +74,22 @@ impl Mesh {
 	pub fn new(
 		canvas: &Canvas,
 		camera: &Camera,
-		vertices: Vec<Vertex>,
-		indices: Vec<u16>,
+		vertices: &[Vertex],
+		indices: &[u32],
 	) -> Self {
 		let vb = canvas
 			.device
 			.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 				label: Some("mesh vertex_buffer"),
-				usage: wgpu::BufferUsages::VERTEX,
-				contents: bytemuck::cast_slice(&vertices),
+				usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+				contents: bytemuck::cast_slice(vertices),
 			});
 		let ib = canvas
 			.device
 			.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 				label: Some("mesh index_buffer"),
 				usage: wgpu::BufferUsages::INDEX,
-				contents: bytemuck::cast_slice(&indices),
+				contents: bytemuck::cast_slice(indices),
 			});
 		let tb =
 			canvas
@@ -49,31 +99,57 @@ impl Mesh {
 					usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
 					contents: bytemuck::cast_slice(&[Mat4::IDENTITY]),
 				});
+		let cb =
+			canvas
+				.device
+				.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("mesh clip_buffer"),
+					usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+					contents: bytemuck::cast_slice(&[0.0f32; 4]),
+				});
 
 		let transform_layout =
 			canvas
 				.device
 				.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 					label: Some("mesh transform_layout"),
-					entries: &[wgpu::BindGroupLayoutEntry {
-						ty: wgpu::BindingType::Buffer {
-							ty: wgpu::BufferBindingType::Uniform,
-							has_dynamic_offset: false,
-							min_binding_size: None,
+					entries: &[
+						wgpu::BindGroupLayoutEntry {
+							ty: wgpu::BindingType::Buffer {
+								ty: wgpu::BufferBindingType::Uniform,
+								has_dynamic_offset: false,
+								min_binding_size: None,
+							},
+							binding: 0,
+							visibility: wgpu::ShaderStages::VERTEX,
+							count: None,
 						},
-						binding: 0,
-						visibility: wgpu::ShaderStages::VERTEX,
-						count: None,
-					}],
+						wgpu::BindGroupLayoutEntry {
+							ty: wgpu::BindingType::Buffer {
+								ty: wgpu::BufferBindingType::Uniform,
+								has_dynamic_offset: false,
+								min_binding_size: None,
+							},
+							binding: 1,
+							visibility: wgpu::ShaderStages::FRAGMENT,
+							count: None,
+						},
+					],
 				});
 
 		let tg = canvas.device.create_bind_group(&wgpu::BindGroupDescriptor {
 			label: Some("mesh transform_group"),
 			layout: &transform_layout,
-			entries: &[wgpu::BindGroupEntry {
-				binding: 0,
-				resource: tb.as_entire_binding(),
-			}],
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: tb.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: cb.as_entire_binding(),
+				},
+			],
 		});
 
 		let pipeline_layout =
@@ -115,7 +191,12 @@ impl Mesh {
 				buffers: &[wgpu::VertexBufferLayout {
 					step_mode: wgpu::VertexStepMode::Vertex,
 					array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-					attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+					attributes: &wgpu::vertex_attr_array![
+						0 => Float32x3,
+						1 => Float32x3,
+						2 => Float32x3,
+						3 => Float32,
+					],
 				}],
 			},
 			fragment: Some(wgpu::FragmentState {
@@ -141,18 +222,24 @@ impl Mesh {
 		let rpb = canvas.device.create_render_pipeline(&rp_descriptor);
 		Self {
 			rpf, rpb,
-			vb, ib, tb,
+			vb, ib, tb, cb,
 			tg,
 			ilen: indices.len() as u32,
 			transform: Mat4::IDENTITY,
 		}
 	}
 
+	/// Loads the triangles of an OBJ, STL or PLY file, see `read_mesh`.
+	pub fn load(canvas: &Canvas, camera: &Camera, path: &std::path::Path) -> std::io::Result<Self> {
+		let (vertices, indices) = read_mesh(path)?;
+		Ok(Self::new(canvas, camera, &vertices, &indices))
+	}
+
 	pub fn render<'r>(&'r self, render_pass: &mut wgpu::RenderPass<'r>, camera: &'r Camera) {
 		render_pass.set_bind_group(0, &camera.group, &[]);
 		render_pass.set_bind_group(1, &self.tg, &[]);
 		render_pass.set_vertex_buffer(0, self.vb.slice(..));
-		render_pass.set_index_buffer(self.ib.slice(..), wgpu::IndexFormat::Uint16);
+		render_pass.set_index_buffer(self.ib.slice(..), wgpu::IndexFormat::Uint32);
 
 		render_pass.set_pipeline(&self.rpb);
 		render_pass.draw_indexed(0..self.ilen, 0, 0..1);
@@ -160,9 +247,21 @@ impl Mesh {
 		render_pass.draw_indexed(0..self.ilen, 0, 0..1);
 	}
 
+	/// Overwrites the vertices, there must be as many as the mesh was created with.
+	pub fn update_vertices(&self, queue: &wgpu::Queue, vertices: &[Vertex]) {
+		queue.write_buffer(&self.vb, 0, bytemuck::cast_slice(vertices));
+	}
+
 	pub fn update_transform_buffer(&self, queue: &wgpu::Queue) {
 		queue.write_buffer(&self.tb, 0, bytemuck::cast_slice(&[self.transform]));
 	}
+
+	/// Cuts away the part of the mesh on the side the normal of the plane, given in world
+	/// space, points to. `None` shows the whole mesh.
+	pub fn set_clip(&self, queue: &wgpu::Queue, plane: Option<Plane>) {
+		let clip = plane.map_or([0.0; 4], |plane| plane.normal.extend(plane.offset).to_array());
+		queue.write_buffer(&self.cb, 0, bytemuck::cast_slice(&clip));
+	}
 }
 
 pub struct Quad {
@@ -243,7 +342,7 @@ impl Quad {
 					}),
 					depth_stencil: Some(wgpu::DepthStencilState {
 						format: wgpu::TextureFormat::Depth32Float,
-						depth_write_enabled: true,
+						depth_write_enabled: false,
 						depth_compare: wgpu::CompareFunction::Less,
 						stencil: wgpu::StencilState::default(),
 						bias: wgpu::DepthBiasState::default(),
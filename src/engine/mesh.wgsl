@@ -1,32 +1,54 @@
 struct VertexInput {
 	@location(0) position: vec3<f32>,
 	@location(1) color: vec3<f32>,
+	@location(2) normal: vec3<f32>,
+	@location(3) alpha: f32,
 }
 
 struct FragmentInput {
 	@builtin(position) position: vec4<f32>,
 	@location(0) color: vec3<f32>,
+	@location(1) normal: vec3<f32>,
+	@location(2) alpha: f32,
+	@location(3) world: vec3<f32>,
 }
 
 @group(0) @binding(0) var<uniform> proj: mat4x4<f32>;
 @group(0) @binding(1) var<uniform> view: mat4x4<f32>;
 @group(1) @binding(0) var<uniform> tran: mat4x4<f32>;
+// Normal and offset of the clipping plane in world space, all zeros clips nothing.
+@group(1) @binding(1) var<uniform> clip: vec4<f32>;
 
 @vertex
 fn vs_main(input: VertexInput) -> FragmentInput {
 	var output: FragmentInput;
-	output.position = proj * view * tran * vec4<f32>(input.position, 1.0);
+	let world = tran * vec4<f32>(input.position, 1.0);
+	output.position = proj * view * world;
+	output.world = world.xyz;
 	output.color = input.color;
+	output.normal = (view * tran * vec4<f32>(input.normal, 0.0)).xyz;
+	output.alpha = input.alpha;
 	return output;
 }
 
 @fragment
 fn fs_main(input: FragmentInput) -> @location(0) vec4<f32> {
-	var r = input.color.r;
-	var g = input.color.g;
-	var b = input.color.b;
-	var a = 0.5;
+	// Cut away what lies on the side the normal of the plane points to.
+	if (dot(clip.xyz, input.world) > clip.w) {
+		discard;
+	}
+
+	// Light coming from the camera, both sides of the surface are lit.
+	var normal = input.normal;
+	if (dot(normal, normal) > 0.0) {
+		normal = normalize(normal);
+	}
+	var color = input.color * (0.4 + 0.6 * abs(normal.z));
+	var r = color.r;
+	var g = color.g;
+	var b = color.b;
+	var a = 0.5 * input.alpha;
 	var z = input.position.z;
 	var weight = max(min(1.0, max(max(r, g), b) * a), a) * z;
-	return vec4<f32>(input.color * a, a) * weight;
+	return vec4<f32>(color * a, a) * weight;
 }
\ No newline at end of file
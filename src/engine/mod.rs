@@ -1,7 +1,24 @@
 mod camera;
+mod capture;
 mod canvas;
+mod contour;
+mod differential;
+mod domain;
+mod geodesic;
+mod gizmos;
+mod gltf;
+mod import;
+mod intersection;
 mod surface;
+mod lines;
 mod mesh;
+mod ply;
+mod script;
+mod stats;
+mod svg;
+mod table;
+mod tessellation;
+mod text;
 
 use winit::{
 	event::{ElementState, Event, KeyboardInput, VirtualKeyCode as Key, WindowEvent},
@@ -9,8 +26,25 @@ use winit::{
 };
 
 pub use camera::*;
+pub use capture::*;
 pub use canvas::*;
+pub use contour::*;
+pub use differential::*;
+pub use domain::Domain;
+pub use geodesic::*;
+pub use gizmos::*;
+pub use gltf::*;
+pub use import::*;
+pub use intersection::*;
+pub use lines::*;
 pub use mesh::*;
+pub use ply::*;
+pub use script::*;
+pub use stats::*;
+pub use svg::*;
+pub use table::*;
+pub use tessellation::*;
+pub use text::*;
 pub use surface::*;
 
 pub trait EventHandler {
@@ -20,7 +54,7 @@ pub trait EventHandler {
 	fn get_canvas(&mut self) -> &mut Canvas;
 }
 
-pub fn run<S: EventHandler + 'static>(event_loop: EventLoop<()>, mut state: S) {
+pub fn run<S: EventHandler + 'static>(event_loop: EventLoop<()>, mut state: S) -> ! {
 	event_loop.run(move |event, _, control_flow| {
 		control_flow.set_poll();
 		let canvas = state.get_canvas();
@@ -42,7 +76,11 @@ pub fn run<S: EventHandler + 'static>(event_loop: EventLoop<()>, mut state: S) {
 				_ => {}
 			},
 			Event::RedrawRequested(_) => state.render(),
-			Event::RedrawEventsCleared => canvas.window.request_redraw(),
+			Event::RedrawEventsCleared => {
+				if let Some(window) = &canvas.window {
+					window.request_redraw();
+				}
+			}
 			_ => {}
 		}
 		state.event(&event);
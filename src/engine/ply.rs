@@ -0,0 +1,102 @@
+use super::{Curvature, Geometry};
+use std::io::{self, Write};
+
+/// Writes the triangles of the surface and the vertices they use as PLY, ASCII or binary
+/// little endian. Each vertex has its position and normal in the space `matrix()` maps
+/// to, its color and alpha as bytes, `u` and `v`, then the script's `value` and the other
+/// numbers it returned when there are any, prefixed with `s_` and sorted by name, then
+/// the curvatures if given. Numbers some vertices lack are NaN.
+pub fn write_ply<W: Write>(
+	w: &mut W,
+	geometry: &Geometry,
+	curvatures: Option<&[Option<Curvature>]>,
+	binary: bool,
+) -> io::Result<()> {
+	let (used, indices) = geometry.compact();
+	let samples = || used.iter().map(|&index| &geometry.samples[index as usize]);
+
+	// Property names end at whitespace, the prefix keeps them apart from the other ones.
+	let property = |name: &str| -> String {
+		"s_".chars().chain(name.chars().map(|c| if c.is_whitespace() { '_' } else { c })).collect()
+	};
+	let mut names: Vec<String> = Vec::new();
+	if samples().any(|s| s.value.is_some()) {
+		names.push("value".into());
+	}
+	let first_scalar = names.len();
+	let scalars: std::collections::BTreeSet<String> = samples().flat_map(|s| s.scalars.iter().map(|(name, _)| property(name))).collect();
+	names.extend(scalars.iter().cloned());
+	let scalars = scalars.len();
+	if curvatures.is_some() {
+		names.extend(["gaussian_curvature", "mean_curvature", "k1", "k2"].map(String::from));
+	}
+
+	writeln!(w, "ply")?;
+	writeln!(w, "format {} 1.0", if binary { "binary_little_endian" } else { "ascii" })?;
+	writeln!(w, "comment generated by surfaco")?;
+	writeln!(w, "element vertex {}", used.len())?;
+	for name in ["x", "y", "z", "nx", "ny", "nz"] {
+		writeln!(w, "property float {name}")?;
+	}
+	for name in ["red", "green", "blue", "alpha"] {
+		writeln!(w, "property uchar {name}")?;
+	}
+	for name in ["u", "v"].into_iter().chain(names.iter().map(String::as_str)) {
+		writeln!(w, "property float {name}")?;
+	}
+	writeln!(w, "element face {}", indices.len() / 3)?;
+	writeln!(w, "property list uchar int vertex_indices")?;
+	writeln!(w, "end_header")?;
+
+	let normal_matrix = geometry.transform.inverse().transpose();
+	let mut floats = Vec::new();
+	for &index in &used {
+		let (vertex, sample) = (&geometry.vertices[index as usize], &geometry.samples[index as usize]);
+		let [r, g, b] = vertex.color;
+		let color = [r, g, b, vertex.alpha].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+		let position = geometry.transform.transform_point3(vertex.position.into());
+		let normal = normal_matrix.transform_vector3(vertex.normal.into()).normalize_or_zero();
+
+		floats.clear();
+		floats.extend(position.to_array());
+		floats.extend(normal.to_array());
+		floats.extend(sample.uv.to_array());
+		if first_scalar > 0 {
+			floats.push(sample.value.unwrap_or(f32::NAN));
+		}
+		for name in &names[first_scalar..first_scalar + scalars] {
+			let value = sample.scalars.iter().find(|(n, _)| property(n) == *name);
+			floats.push(value.map_or(f32::NAN, |(_, x)| *x));
+		}
+		if let Some(curvatures) = curvatures {
+			let k = curvatures[index as usize];
+			floats.extend(k.map_or([f32::NAN; 4], |k| [k.gaussian, k.mean, k.k1, k.k2]));
+		}
+
+		if binary {
+			for x in &floats[..6] {
+				w.write_all(&x.to_le_bytes())?;
+			}
+			w.write_all(&color)?;
+			for x in &floats[6..] {
+				w.write_all(&x.to_le_bytes())?;
+			}
+		} else {
+			let text = |xs: &[f32]| xs.iter().map(f32::to_string).collect::<Vec<_>>().join(" ");
+			let [r, g, b, a] = color;
+			writeln!(w, "{} {r} {g} {b} {a} {}", text(&floats[..6]), text(&floats[6..]))?;
+		}
+	}
+
+	for face in indices.chunks_exact(3) {
+		if binary {
+			w.write_all(&[3])?;
+			for &index in face {
+				w.write_all(&(index as i32).to_le_bytes())?;
+			}
+		} else {
+			writeln!(w, "3 {} {} {}", face[0], face[1], face[2])?;
+		}
+	}
+	Ok(())
+}
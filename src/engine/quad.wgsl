@@ -17,5 +17,7 @@ fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
 
 @fragment
 fn fs_main() -> @location(0) vec4<f32> {
-	return vec4<f32>(0.1, 0.5, 0.3, 1.0);
+	// Premultiplied and translucent, so the curves cut by the plane show through.
+	let a = 0.4;
+	return vec4<f32>(vec3(0.1, 0.5, 0.3) * a, a);
 }
\ No newline at end of file
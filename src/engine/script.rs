@@ -0,0 +1,428 @@
+use super::{color_map, complex, default_fn, donut, Adaptive, Domain, Levels, SurfaceConfig};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use rhai::{Array, ASTNode, Dynamic, Engine, EvalAltResult, Expr, Map, Position, Scope, AST, FLOAT, INT};
+
+/// A single evaluation of the script's `vertex` function.
+///
+/// Attributes the script leaves out are `None`: a missing color is taken from a color map
+/// over `value` (or the height when there is no value either), and a missing normal is
+/// computed from the mesh.
+#[derive(Clone, Debug)]
+pub struct Sample {
+	/// Point of the parameter domain the sample was taken at.
+	pub uv: Vec2,
+	pub position: Vec3,
+	pub color: Option<Vec3>,
+	pub alpha: f32,
+	pub normal: Option<Vec3>,
+	pub value: Option<f32>,
+	pub hidden: bool,
+	/// Other numbers in the map the script returned, by key, exported along with the mesh.
+	pub scalars: Vec<(String, f32)>,
+}
+
+impl Sample {
+	pub fn new(position: Vec3) -> Self {
+		Self {
+			uv: Vec2::ZERO,
+			position,
+			color: None,
+			alpha: 1.0,
+			normal: None,
+			value: None,
+			hidden: false,
+			scalars: Vec::new(),
+		}
+	}
+}
+
+/// A compiled surface script together with the scope left by its top level statements.
+pub struct Script {
+	engine: Engine,
+	ast: AST,
+	scope: Scope<'static>,
+}
+
+impl Script {
+	pub fn load<P: Into<std::path::PathBuf>>(path: P) -> Result<Self, Box<EvalAltResult>> {
+		let engine = engine();
+		let ast = engine.compile_file(path.into())?;
+		let mut scope = Scope::new();
+		scope.push("u_min", -1.0f32);
+		scope.push("u_max",  1.0f32);
+		scope.push("v_min", -1.0f32);
+		scope.push("v_max",  1.0f32);
+		scope.push("u_segments", 100 as INT);
+		scope.push("v_segments", 100 as INT);
+		scope.push("u_periodic", false);
+		scope.push("v_periodic", false);
+		scope.push("weld_tolerance", 1e-4f32);
+		scope.push("clamp_radius", ());
+		let adaptive = Adaptive::default();
+		scope.push("adaptive", false);
+		scope.push("tolerance", adaptive.tolerance);
+		scope.push("max_triangles", adaptive.max_triangles as INT);
+		scope.push("max_depth", adaptive.max_depth as INT);
+		scope.push("domain", "rect");
+		scope.push("r_min", 0.0f32);
+		scope.push("r_max", 1.0f32);
+		scope.push("contours", 10 as INT);
+		scope.push("geodesics", Array::new());
+		scope.push("time", 0.0f32);
+		scope.push("triangle", to_array([0.0, 0.0, 1.0, 0.0, 0.0, 1.0]).chunks(2).map(|c| c.to_vec().into()).collect::<Array>());
+		engine.run_ast_with_scope(&mut scope, &ast)?;
+
+		Ok(Self { engine, ast, scope })
+	}
+
+	/// Sets `time`, in seconds, for the functions evaluated next. The top level statements
+	/// only ever see zero.
+	pub fn set_time(&mut self, time: f32) {
+		self.scope.set_value("time", time);
+	}
+
+	/// Whether the script reads `time` anywhere, so that it may change as time passes.
+	pub fn uses_time(&self) -> bool {
+		!self.ast.walk(&mut |path: &[ASTNode]| {
+			!matches!(path.last(), Some(ASTNode::Expr(Expr::Variable(x, ..))) if x.1 == "time")
+		})
+	}
+
+	/// Reads the settings the top level statements left, failing if one has the wrong
+	/// type or a segment count isn't positive.
+	pub fn config(&self) -> Result<SurfaceConfig, Box<EvalAltResult>> {
+		Ok(SurfaceConfig::new(
+			self.float("u_min")?,
+			self.float("u_max")?,
+			self.float("v_min")?,
+			self.float("v_max")?,
+			self.count("u_segments")?,
+			self.count("v_segments")?,
+		)
+		.with_periodic(self.flag("u_periodic")?, self.flag("v_periodic")?)
+		.with_weld_tolerance(self.float("weld_tolerance")?)
+		.with_clamp_radius(self.scope.get_value::<Dynamic>("clamp_radius").and_then(|r| to_float(&r).ok()))
+		.with_adaptive(self.adaptive()?)
+		.with_domain(self.domain()?))
+	}
+
+	/// The number `name` is set to, integers included.
+	fn float(&self, name: &str) -> Result<f32, Box<EvalAltResult>> {
+		let value = self.scope.get_value::<Dynamic>(name).unwrap_or_default();
+		to_float(&value).map_err(|_| setting(name, "a number", value.type_name()))
+	}
+
+	/// Whether `name` is set to true.
+	fn flag(&self, name: &str) -> Result<bool, Box<EvalAltResult>> {
+		let value = self.scope.get_value::<Dynamic>(name).unwrap_or_default();
+		value.as_bool().map_err(|actual| setting(name, "true or false", actual))
+	}
+
+	/// The positive integer `name` is set to.
+	fn count(&self, name: &str) -> Result<usize, Box<EvalAltResult>> {
+		let value = self.scope.get_value::<Dynamic>(name).unwrap_or_default();
+		match value.as_int() {
+			Ok(n) if n > 0 => Ok(n as usize),
+			Ok(n) => Err(setting(name, "positive", &n.to_string())),
+			Err(actual) => Err(setting(name, "an integer", actual)),
+		}
+	}
+
+	/// Reads `domain`, which may be `"rect"`, `"disk"` (using `r_min` and `r_max`) or
+	/// `"triangle"` (using the three `[u, v]` points in `triangle`). Defining
+	/// `in_domain(u, v)` restricts the rectangle to where it returns true.
+	fn domain(&self) -> Result<Domain, Box<EvalAltResult>> {
+		if self.has_fn("in_domain", 2) {
+			return Ok(Domain::Predicate);
+		}
+		let domain = self.scope.get_value::<Dynamic>("domain").unwrap_or_default();
+		let expected = "\"rect\", \"disk\" or \"triangle\"";
+		let name = domain.into_immutable_string().map_err(|actual| setting("domain", expected, actual))?;
+		Ok(match name.as_str() {
+			"rect" => Domain::Rect,
+			"disk" | "annulus" => Domain::Disk {
+				r_min: self.float("r_min")?,
+				r_max: self.float("r_max")?,
+			},
+			"triangle" => {
+				let corners = self.scope.get_value::<Dynamic>("triangle").unwrap_or_default();
+				let actual = corners.type_name();
+				let corners = corners
+					.into_array()
+					.ok()
+					.and_then(|corners| corners.into_iter().map(|c| to_floats::<2>(c).ok()).collect::<Option<Vec<_>>>())
+					.and_then(|corners| <[[f32; 2]; 3]>::try_from(corners).ok())
+					.ok_or_else(|| setting("triangle", "three [u, v] points", actual))?;
+				Domain::Triangle(corners.map(Vec2::from))
+			}
+			name => return Err(setting("domain", expected, &format!("\"{name}\""))),
+		})
+	}
+
+	/// Reads `contours`, either a number of levels or an array with their values.
+	pub fn levels(&self) -> Levels {
+		match self.scope.get_value::<Array>("contours") {
+			Some(levels) => Levels::Values(levels.into_iter().filter_map(|x| to_float(&x).ok()).collect()),
+			None => Levels::Count(self.scope.get_value::<INT>("contours").unwrap_or(10).max(0) as usize),
+		}
+	}
+
+	/// Reads `geodesics`, an array of `[u, v, du, dv]` with the start and the initial
+	/// direction of each geodesic.
+	pub fn geodesics(&self) -> Vec<(Vec2, Vec2)> {
+		self.scope
+			.get_value::<Array>("geodesics")
+			.unwrap_or_default()
+			.into_iter()
+			.filter_map(|g| to_floats::<4>(g).ok())
+			.map(|[u, v, du, dv]| (Vec2::new(u, v), Vec2::new(du, dv)))
+			.collect()
+	}
+
+	pub fn has_fn(&self, name: &str, params: usize) -> bool {
+		self.ast
+			.iter_functions()
+			.any(|f| f.name == name && f.params.len() == params)
+	}
+
+	/// Calls `in_domain(u, v)`.
+	pub fn in_domain(&mut self, u: f32, v: f32) -> Result<bool, Box<EvalAltResult>> {
+		self.engine.call_fn(&mut self.scope, &self.ast, "in_domain", (u, v))
+	}
+
+	/// Calls `field(u, v)`, a tangent vector in parameter space given either as
+	/// `[du, dv]` or as the `x` and `y` of a `vec3`.
+	pub fn field(&mut self, u: f32, v: f32) -> Result<Vec2, Box<EvalAltResult>> {
+		let value = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.ast, "field", (u, v))?;
+		if value.is::<Vec3>() {
+			Ok(value.cast::<Vec3>().truncate())
+		} else {
+			to_floats::<2>(value).map(Vec2::from)
+		}
+	}
+
+	/// Reads `adaptive`, `tolerance`, `max_triangles` and `max_depth`.
+	fn adaptive(&self) -> Result<Option<Adaptive>, Box<EvalAltResult>> {
+		if !self.flag("adaptive")? {
+			return Ok(None);
+		}
+		let max_depth = self.scope.get_value::<Dynamic>("max_depth").unwrap_or_default();
+		Ok(Some(Adaptive {
+			tolerance: self.float("tolerance")?,
+			max_triangles: self.count("max_triangles")?,
+			max_depth: max_depth.as_int().map_err(|actual| setting("max_depth", "an integer", actual))?.clamp(0, 16) as _,
+		}))
+	}
+
+	/// Calls `vertex(u, v)`, which may return either a flat `[x, y, z, r, g, b]` array or
+	/// a map such as `#{pos: vec3(x, y, z), color: vec3(r, g, b)}`. Besides `pos`, the map
+	/// accepts the optional keys `color`, `alpha`, `normal`, `value` and `hidden` (or its
+	/// alias `discard`), any other key with a number is kept in `scalars`. Returning `()`
+	/// discards the point altogether.
+	pub fn vertex(&mut self, u: f32, v: f32) -> Result<Sample, Box<EvalAltResult>> {
+		let value = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.ast, "vertex", (u, v))?;
+		if value.is_unit() {
+			let mut sample = Sample::new(Vec3::ZERO);
+			sample.hidden = true;
+			Ok(sample)
+		} else if value.is_map() {
+			let mut map = value.cast::<Map>();
+			let mut sample = match map.remove("pos") {
+				Some(pos) => Sample::new(to_vec3(pos)?),
+				None => return Err(mismatch("map with a `pos` key", "map")),
+			};
+			if let Some(color) = map.remove("color") {
+				sample.color = Some(to_vec3(color)?);
+			}
+			if let Some(alpha) = map.remove("alpha") {
+				sample.alpha = to_float(&alpha)?;
+			}
+			if let Some(normal) = map.remove("normal") {
+				sample.normal = Some(to_vec3(normal)?);
+			}
+			if let Some(value) = map.remove("value") {
+				sample.value = Some(to_float(&value)?);
+			}
+			for key in ["hidden", "discard"] {
+				if let Some(hidden) = map.remove(key) {
+					sample.hidden |= hidden.as_bool().map_err(|actual| mismatch("bool", actual))?;
+				}
+			}
+			sample.scalars = map
+				.into_iter()
+				.filter_map(|(key, value)| Some((key.to_string(), to_float(&value).ok()?)))
+				.collect();
+			Ok(sample)
+		} else {
+			let [x, y, z, r, g, b] = to_floats::<6>(value)?;
+			let mut sample = Sample::new(Vec3::new(x, y, z));
+			sample.color = Some(Vec3::new(r, g, b));
+			Ok(sample)
+		}
+	}
+
+	/// Position of `vertex(u, v)`, `None` if it is hidden, not finite or fails.
+	pub fn position(&mut self, uv: Vec2) -> Option<Vec3> {
+		let sample = self.vertex(uv.x, uv.y).ok()?;
+		(!sample.hidden && sample.position.is_finite()).then_some(sample.position)
+	}
+
+	/// Calls `matrix()`, which may return either a `mat4` or a flat row-major array of
+	/// sixteen floats.
+	pub fn matrix(&mut self) -> Result<Mat4, Box<EvalAltResult>> {
+		let value = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.ast, "matrix", ())?;
+		if value.is::<Mat4>() {
+			Ok(value.cast())
+		} else {
+			Ok(Mat4::from_cols_array(&to_floats(value)?).transpose())
+		}
+	}
+}
+
+fn mismatch(expected: &str, actual: &str) -> Box<EvalAltResult> {
+	EvalAltResult::ErrorMismatchOutputType(expected.into(), actual.into(), Position::NONE).into()
+}
+
+/// Error for a setting of the script that isn't what it should be.
+fn setting(name: &str, expected: &str, actual: &str) -> Box<EvalAltResult> {
+	EvalAltResult::ErrorRuntime(format!("{name} must be {expected}, not {actual}").into(), Position::NONE).into()
+}
+
+fn to_float(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+	value
+		.as_float()
+		.or_else(|_| value.as_int().map(|i| i as f32))
+		.map_err(|actual| mismatch("float", actual))
+}
+
+fn to_floats<const N: usize>(value: Dynamic) -> Result<[f32; N], Box<EvalAltResult>> {
+	let actual = value.type_name();
+	let array = value.into_array().map_err(|_| mismatch("array", actual))?;
+	if array.len() != N {
+		return Err(mismatch(&format!("array of {N} floats"), &format!("array of {}", array.len())));
+	}
+	let mut floats = [0.0; N];
+	for (f, value) in floats.iter_mut().zip(&array) {
+		*f = to_float(value)?;
+	}
+	Ok(floats)
+}
+
+fn to_vec3(value: Dynamic) -> Result<Vec3, Box<EvalAltResult>> {
+	if value.is::<Vec3>() {
+		Ok(value.cast())
+	} else {
+		to_floats(value).map(Vec3::from)
+	}
+}
+
+fn to_array(floats: impl IntoIterator<Item = f32>) -> Array {
+	floats.into_iter().map(Dynamic::from_float).collect()
+}
+
+/// Builds the scripting engine with the surface helpers and the `vec3`, `mat4` and
+/// `quat` types registered.
+fn engine() -> Engine {
+	let mut engine = Engine::new();
+	engine.register_fn("color_map", |min: f32, max: f32, val: f32| to_array(color_map(min, max, val)));
+	engine.register_fn("complex", |u: f32, v: f32| to_array(complex(u, v)));
+	engine.register_fn("donut", |u: f32, v: f32, dr: f32, er: f32| to_array(donut(u, v, dr, er)));
+	engine.register_fn("default_fn", |u: f32, v: f32| to_array(default_fn(u, v)));
+
+	register_vec3(&mut engine);
+	register_mat4(&mut engine);
+	register_quat(&mut engine);
+	engine
+}
+
+fn register_vec3(engine: &mut Engine) {
+	engine
+		.register_type_with_name::<Vec3>("vec3")
+		.register_fn("vec3", Vec3::new)
+		.register_fn("vec3", |x: Dynamic, y: Dynamic, z: Dynamic| -> Result<Vec3, Box<EvalAltResult>> {
+			Ok(Vec3::new(to_float(&x)?, to_float(&y)?, to_float(&z)?))
+		})
+		.register_fn("vec3", Vec3::splat)
+		.register_get_set("x", |v: &mut Vec3| v.x, |v: &mut Vec3, x: FLOAT| v.x = x)
+		.register_get_set("y", |v: &mut Vec3| v.y, |v: &mut Vec3, y: FLOAT| v.y = y)
+		.register_get_set("z", |v: &mut Vec3| v.z, |v: &mut Vec3, z: FLOAT| v.z = z)
+		.register_fn("+", |a: Vec3, b: Vec3| a + b)
+		.register_fn("-", |a: Vec3, b: Vec3| a - b)
+		.register_fn("-", |a: Vec3| -a)
+		.register_fn("*", |a: Vec3, b: Vec3| a * b)
+		.register_fn("*", |a: Vec3, s: FLOAT| a * s)
+		.register_fn("*", |s: FLOAT, a: Vec3| s * a)
+		.register_fn("/", |a: Vec3, b: Vec3| a / b)
+		.register_fn("/", |a: Vec3, s: FLOAT| a / s)
+		.register_fn("==", |a: Vec3, b: Vec3| a == b)
+		.register_fn("!=", |a: Vec3, b: Vec3| a != b)
+		.register_fn("dot", |a: Vec3, b: Vec3| a.dot(b))
+		.register_fn("cross", |a: Vec3, b: Vec3| a.cross(b))
+		.register_fn("length", |a: Vec3| a.length())
+		.register_fn("distance", |a: Vec3, b: Vec3| a.distance(b))
+		.register_fn("normalize", |a: Vec3| a.normalize_or_zero())
+		.register_fn("lerp", |a: Vec3, b: Vec3, t: FLOAT| a.lerp(b, t))
+		.register_fn("rotate_x", |a: Vec3, angle: FLOAT| Quat::from_rotation_x(angle) * a)
+		.register_fn("rotate_y", |a: Vec3, angle: FLOAT| Quat::from_rotation_y(angle) * a)
+		.register_fn("rotate_z", |a: Vec3, angle: FLOAT| Quat::from_rotation_z(angle) * a)
+		.register_fn("to_array", |a: Vec3| to_array(a.to_array()))
+		.register_fn("to_string", |a: &mut Vec3| format!("vec3({}, {}, {})", a.x, a.y, a.z))
+		.register_fn("to_debug", |a: &mut Vec3| format!("{a:?}"));
+}
+
+fn register_mat4(engine: &mut Engine) {
+	engine
+		.register_type_with_name::<Mat4>("mat4")
+		.register_fn("mat4", || Mat4::IDENTITY)
+		.register_fn("mat4", Mat4::from_quat)
+		.register_fn("mat4", |rows: Array| -> Result<Mat4, Box<EvalAltResult>> {
+			Ok(Mat4::from_cols_array(&to_floats(rows.into())?).transpose())
+		})
+		.register_fn("rotate_x", Mat4::from_rotation_x)
+		.register_fn("rotate_y", Mat4::from_rotation_y)
+		.register_fn("rotate_z", Mat4::from_rotation_z)
+		.register_fn("translate", Mat4::from_translation)
+		.register_fn("scale", Mat4::from_scale)
+		.register_fn("scale", |s: FLOAT| Mat4::from_scale(Vec3::splat(s)))
+		.register_fn("rotate_x", |m: Mat4, angle: FLOAT| m * Mat4::from_rotation_x(angle))
+		.register_fn("rotate_y", |m: Mat4, angle: FLOAT| m * Mat4::from_rotation_y(angle))
+		.register_fn("rotate_z", |m: Mat4, angle: FLOAT| m * Mat4::from_rotation_z(angle))
+		.register_fn("rotate", |m: Mat4, q: Quat| m * Mat4::from_quat(q))
+		.register_fn("translate", |m: Mat4, t: Vec3| m * Mat4::from_translation(t))
+		.register_fn("scale", |m: Mat4, s: Vec3| m * Mat4::from_scale(s))
+		.register_fn("scale", |m: Mat4, s: FLOAT| m * Mat4::from_scale(Vec3::splat(s)))
+		.register_fn("*", |a: Mat4, b: Mat4| a * b)
+		.register_fn("*", |m: Mat4, p: Vec3| m.transform_point3(p))
+		.register_fn("==", |a: Mat4, b: Mat4| a == b)
+		.register_fn("!=", |a: Mat4, b: Mat4| a != b)
+		.register_fn("transpose", |m: Mat4| m.transpose())
+		.register_fn("inverse", |m: Mat4| m.inverse())
+		.register_fn("transform_vector", |m: Mat4, v: Vec3| m.transform_vector3(v))
+		.register_fn("to_array", |m: Mat4| to_array(m.transpose().to_cols_array()))
+		.register_fn("to_string", |m: &mut Mat4| format!("{m}"))
+		.register_fn("to_debug", |m: &mut Mat4| format!("{m:?}"));
+}
+
+fn register_quat(engine: &mut Engine) {
+	engine
+		.register_type_with_name::<Quat>("quat")
+		.register_fn("quat", || Quat::IDENTITY)
+		.register_fn("quat", |axis: Vec3, angle: FLOAT| Quat::from_axis_angle(axis.normalize_or_zero(), angle))
+		.register_fn("quat", |x: FLOAT, y: FLOAT, z: FLOAT, w: FLOAT| Quat::from_xyzw(x, y, z, w).normalize())
+		.register_fn("quat_x", Quat::from_rotation_x)
+		.register_fn("quat_y", Quat::from_rotation_y)
+		.register_fn("quat_z", Quat::from_rotation_z)
+		.register_get("x", |q: &mut Quat| q.x)
+		.register_get("y", |q: &mut Quat| q.y)
+		.register_get("z", |q: &mut Quat| q.z)
+		.register_get("w", |q: &mut Quat| q.w)
+		.register_fn("*", |a: Quat, b: Quat| a * b)
+		.register_fn("*", |q: Quat, v: Vec3| q * v)
+		.register_fn("==", |a: Quat, b: Quat| a == b)
+		.register_fn("!=", |a: Quat, b: Quat| a != b)
+		.register_fn("normalize", |q: Quat| q.normalize())
+		.register_fn("inverse", |q: Quat| q.inverse())
+		.register_fn("slerp", |a: Quat, b: Quat, t: FLOAT| a.slerp(b, t))
+		.register_fn("to_string", |q: &mut Quat| format!("quat({}, {}, {}, {})", q.x, q.y, q.z, q.w))
+		.register_fn("to_debug", |q: &mut Quat| format!("{q:?}"));
+}
@@ -0,0 +1,121 @@
+use super::Geometry;
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// Measurements of the triangles of a surface, in the space its `matrix()` maps to.
+#[derive(Clone, Debug)]
+pub struct Stats {
+	pub vertices: usize,
+	pub triangles: usize,
+	pub area: f32,
+	/// Enclosed volume, only known when every edge is shared by exactly two triangles.
+	pub volume: Option<f32>,
+	pub bbox_min: Vec3,
+	pub bbox_max: Vec3,
+	/// Area weighted centroid of the surface.
+	pub centroid: Vec3,
+	pub min_edge: f32,
+	pub max_edge: f32,
+	/// Samples whose position was NaN or infinite, their triangles were dropped.
+	pub bad_samples: usize,
+}
+
+impl Stats {
+	pub fn new(geometry: &Geometry) -> Self {
+		let Geometry { vertices, indices, transform, .. } = geometry;
+		let positions: Vec<Vec3> = vertices.iter().map(|v| transform.transform_point3(v.position.into())).collect();
+		let position = |i: u32| positions[i as usize];
+
+		let mut used = vec![false; vertices.len()];
+		let mut edges = HashMap::new();
+		let mut area = 0.0;
+		let mut volume = 0.0;
+		let mut centroid = Vec3::ZERO;
+		for face in indices.chunks_exact(3) {
+			let [a, b, c] = [face[0], face[1], face[2]].map(position);
+			let face_area = (b - a).cross(c - a).length() / 2.0;
+			area += face_area;
+			centroid += face_area * (a + b + c) / 3.0;
+			volume += a.dot(b.cross(c)) / 6.0;
+			for k in 0..3 {
+				let (i, j) = (face[k], face[(k + 1) % 3]);
+				used[i as usize] = true;
+				*edges.entry((i.min(j), i.max(j))).or_insert(0u32) += 1;
+			}
+		}
+
+		let (mut bbox_min, mut bbox_max) = (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY));
+		for (&position, _) in positions.iter().zip(&used).filter(|(_, &used)| used) {
+			bbox_min = bbox_min.min(position);
+			bbox_max = bbox_max.max(position);
+		}
+
+		let (mut min_edge, mut max_edge) = (f32::INFINITY, 0.0f32);
+		for &(i, j) in edges.keys() {
+			let length = position(i).distance(position(j));
+			min_edge = min_edge.min(length);
+			max_edge = max_edge.max(length);
+		}
+
+		let closed = !edges.is_empty() && edges.values().all(|&count| count == 2);
+		Self {
+			vertices: used.iter().filter(|&&used| used).count(),
+			triangles: indices.len() / 3,
+			area,
+			volume: closed.then_some(volume.abs()),
+			bbox_min,
+			bbox_max,
+			centroid: if area > 0.0 { centroid / area } else { Vec3::ZERO },
+			min_edge,
+			max_edge,
+			bad_samples: geometry.bad_samples,
+		}
+	}
+
+	/// Measurements an empty mesh doesn't have, like its bounding box, are `null`.
+	pub fn to_json(&self) -> String {
+		let number = |x: f32| if x.is_finite() { x.to_string() } else { "null".into() };
+		let vec = |v: Vec3| if v.is_finite() { format!("[{}, {}, {}]", v.x, v.y, v.z) } else { "null".into() };
+		format!(
+			concat!(
+				"{{\n",
+				"  \"vertices\": {},\n",
+				"  \"triangles\": {},\n",
+				"  \"area\": {},\n",
+				"  \"volume\": {},\n",
+				"  \"bbox\": {{\"min\": {}, \"max\": {}}},\n",
+				"  \"centroid\": {},\n",
+				"  \"min_edge\": {},\n",
+				"  \"max_edge\": {},\n",
+				"  \"bad_samples\": {}\n",
+				"}}",
+			),
+			self.vertices,
+			self.triangles,
+			number(self.area),
+			self.volume.map_or("null".into(), number),
+			vec(self.bbox_min),
+			vec(self.bbox_max),
+			vec(self.centroid),
+			number(self.min_edge),
+			number(self.max_edge),
+			self.bad_samples,
+		)
+	}
+}
+
+impl std::fmt::Display for Stats {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "vertices:  {}", self.vertices)?;
+		writeln!(f, "triangles: {}", self.triangles)?;
+		writeln!(f, "area:      {}", self.area)?;
+		match self.volume {
+			Some(volume) => writeln!(f, "volume:    {volume}")?,
+			None => writeln!(f, "volume:    (open mesh)")?,
+		}
+		writeln!(f, "bbox:      {} .. {}", self.bbox_min, self.bbox_max)?;
+		writeln!(f, "centroid:  {}", self.centroid)?;
+		writeln!(f, "edges:     {} .. {}", self.min_edge, self.max_edge)?;
+		write!(f, "dropped:   {} samples NaN or infinite", self.bad_samples)
+	}
+}
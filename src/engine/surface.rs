@@ -1,5 +1,7 @@
-use super::{Camera, Canvas, Mesh, Vertex};
-use rhai::{Engine, AST, Dynamic, Array, Scope};
+use std::cell::RefCell;
+use glam::{Mat4, Vec2, Vec3};
+use rhai::EvalAltResult;
+use super::{arrow, bounding_box, chain, contours, rulers, domain, geodesic, intersect, polyline, Plane, raycast, segment, shortest_path, fill_normals, Curvature, Derivatives, LineVertex, Levels, Lines, tessellate, weld, Adaptive, Domain, Camera, Canvas, Drawing, line_drawing, Mesh, Sample, Script, Text, Vertex};
 
 pub fn color_map(min: f32, max: f32, val: f32) -> [f32; 3] {
 	let val = (val.min(max).max(min) - min) / (max - min);
@@ -34,6 +36,28 @@ pub fn default_fn(u: f32, v: f32) -> Vec<f32> {
 	].concat()
 }
 
+/// Turns samples into vertices, coloring the ones without a color by mapping their `value`,
+/// or their height, over the range taken by all the visible samples.
+pub fn to_vertices(samples: &[Sample]) -> Vec<Vertex> {
+	let scalar = |s: &Sample| s.value.unwrap_or(s.position.z);
+	let (min, max) = samples
+		.iter()
+		.filter(|s| s.color.is_none() && !s.hidden)
+		.map(scalar)
+		.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| (min.min(x), max.max(x)));
+	let max = if max > min { max } else { min + 1.0 };
+
+	samples
+		.iter()
+		.map(|s| Vertex {
+			position: s.position.into(),
+			color: s.color.map_or_else(|| color_map(min, max, scalar(s)), Into::into),
+			normal: s.normal.unwrap_or_default().into(),
+			alpha: if s.hidden { 0.0 } else { s.alpha },
+		})
+		.collect()
+}
+
 #[derive(Default)]
 pub struct SurfaceConfig {
 	u_min: f32,
@@ -42,20 +66,15 @@ pub struct SurfaceConfig {
 	v_max: f32,
 	u_segments: usize,
 	v_segments: usize,
+	u_periodic: bool,
+	v_periodic: bool,
+	weld_tolerance: f32,
+	clamp_radius: Option<f32>,
+	adaptive: Option<Adaptive>,
+	domain: Domain,
 }
 
 impl SurfaceConfig {
-	fn from_scope(scope: &Scope) -> Self {
-		Self::new(
-			scope.get_value("u_min").unwrap(),
-			scope.get_value("u_max").unwrap(),
-			scope.get_value("v_min").unwrap(),
-			scope.get_value("v_max").unwrap(),
-			scope.get_value::<i64>("u_segments").unwrap() as _,
-			scope.get_value::<i64>("v_segments").unwrap() as _,
-		)
-	}
-
 	pub fn new(
 		u_min: f32,
 		u_max: f32,
@@ -71,40 +90,199 @@ impl SurfaceConfig {
 			v_max,
 			u_segments,
 			v_segments,
+			u_periodic: false,
+			v_periodic: false,
+			weld_tolerance: 1e-4,
+			clamp_radius: None,
+			adaptive: None,
+			domain: Domain::Rect,
+		}
+	}
+
+	/// Samples a different region of the parameter plane, the rectangle bounds only
+	/// matter for `Domain::Rect` and `Domain::Predicate`.
+	pub fn with_domain(mut self, domain: Domain) -> Self {
+		self.domain = domain;
+		self
+	}
+
+	/// Declares parameters whose last row or column is the same as the first one, those
+	/// are sampled once and the triangles wrap around.
+	pub fn with_periodic(mut self, u_periodic: bool, v_periodic: bool) -> Self {
+		self.u_periodic = u_periodic;
+		self.v_periodic = v_periodic;
+		self
+	}
+
+	/// Border vertices closer than `tolerance` get merged, zero disables welding.
+	pub fn with_weld_tolerance(mut self, tolerance: f32) -> Self {
+		self.weld_tolerance = tolerance;
+		self
+	}
+
+	/// Refines the grid where the surface bends instead of sampling it uniformly.
+	pub fn with_adaptive(mut self, adaptive: Option<Adaptive>) -> Self {
+		self.adaptive = adaptive;
+		self
+	}
+
+	/// Pulls every finite sample farther than `radius` from the origin back onto the
+	/// sphere of that radius.
+	pub fn with_clamp_radius(mut self, radius: Option<f32>) -> Self {
+		self.clamp_radius = radius;
+		self
+	}
+
+	/// Adaptive refinement and periodic parameters only apply to the rectangle, asking for
+	/// them with another domain is an error rather than being ignored.
+	pub fn check(&self) -> Result<(), String> {
+		let domain = match self.domain {
+			Domain::Rect => return Ok(()),
+			Domain::Disk { .. } => "the disk domain",
+			Domain::Triangle(_) => "the triangle domain",
+			Domain::Predicate => "in_domain(u, v)",
+		};
+		if self.adaptive.is_some() {
+			return Err(format!("adaptive only works on the rectangle, not with {domain}"));
+		}
+		if self.u_periodic || self.v_periodic {
+			return Err(format!("u_periodic and v_periodic only work on the rectangle, not with {domain}"));
+		}
+		Ok(())
+	}
+
+	/// Hides the sample if its position is not finite, otherwise clamps it if a clamp
+	/// radius is set. Returns whether the position was finite.
+	fn sanitize(&self, sample: &mut Sample) -> bool {
+		if !sample.position.is_finite() {
+			sample.hidden = true;
+			return false;
+		}
+		if let Some(radius) = self.clamp_radius {
+			sample.position = sample.position.clamp_length_max(radius);
+		}
+		if sample.normal.is_some_and(|n| !n.is_finite()) {
+			sample.normal = None;
+		}
+		true
+	}
+
+	/// Samples `f` over the domain and triangulates it, returning the samples, the
+	/// triangles over them and how many samples were NaN or infinite. `in_domain` is only
+	/// called for `Domain::Predicate`.
+	pub fn generate(
+		&self,
+		mut f: impl FnMut(f32, f32) -> Sample,
+		in_domain: impl FnMut(f32, f32) -> bool,
+	) -> (Vec<Sample>, Vec<u32>, usize) {
+		let mut bad = 0;
+		let mut f = |u, v| {
+			let mut sample = f(u, v);
+			sample.uv = glam::vec2(u, v);
+			if !self.sanitize(&mut sample) {
+				bad += 1;
+			}
+			sample
+		};
+
+		let rect = (self.u_min, self.u_max, self.v_min, self.v_max);
+		let segments = (self.u_segments, self.v_segments);
+		let triangulation = match self.domain {
+			Domain::Rect => None,
+			Domain::Disk { r_min, r_max } => Some(domain::disk(r_min, r_max, self.u_segments, self.v_segments)),
+			Domain::Triangle(corners) => Some(domain::triangle(corners, self.u_segments)),
+			Domain::Predicate => Some(domain::predicate(rect, segments, in_domain)),
+		};
+
+		let (samples, mut indices) = match (triangulation, self.adaptive) {
+			(Some((points, indices)), _) => {
+				let samples: Vec<_> = points.into_iter().map(|p| f(p.x, p.y)).collect();
+				let indices = indices
+					.chunks_exact(3)
+					.filter(|face| face.iter().all(|&idx| !samples[idx as usize].hidden))
+					.flatten()
+					.copied()
+					.collect();
+				(samples, indices)
+			}
+			(None, Some(adaptive)) => tessellate(
+				rect,
+				segments,
+				(self.u_periodic, self.v_periodic),
+				adaptive,
+				f,
+			),
+			(None, None) => {
+				let samples = self.generate_samples(f);
+				let indices = self.generate_indices(&samples);
+				(samples, indices)
+			}
+		};
+		if self.weld_tolerance > 0.0 {
+			let positions: Vec<_> = samples.iter().map(|s| s.position).collect();
+			weld(&positions, &mut indices, self.weld_tolerance);
+		}
+		(samples, indices, bad)
+	}
+
+	/// Brings `uv` back into the domain across periodic parameters, `None` if it lies
+	/// outside. Predicate domains are only checked against their rectangle.
+	pub fn wrap(&self, uv: Vec2) -> Option<Vec2> {
+		let wrap = |x: f32, min: f32, max: f32, periodic: bool| {
+			if periodic {
+				Some(min + (x - min).rem_euclid(max - min))
+			} else {
+				(min..=max).contains(&x).then_some(x)
+			}
+		};
+		match self.domain {
+			Domain::Rect | Domain::Predicate => Some(Vec2::new(
+				wrap(uv.x, self.u_min, self.u_max, self.u_periodic)?,
+				wrap(uv.y, self.v_min, self.v_max, self.v_periodic)?,
+			)),
+			Domain::Disk { r_min, r_max } => (r_min..=r_max).contains(&uv.length()).then_some(uv),
+			Domain::Triangle([a, b, c]) => {
+				let st = glam::Mat2::from_cols(b - a, c - a).inverse() * (uv - a);
+				(st.min_element() >= 0.0 && st.x + st.y <= 1.0).then_some(uv)
+			}
 		}
 	}
 
-	fn generate_vertices(&self, mut f: impl FnMut(f32, f32) -> [f32; 6]) -> Vec<Vertex> {
+	/// Number of rows and columns of samples, a periodic parameter doesn't repeat the
+	/// first row or column at the end.
+	fn grid_size(&self) -> (usize, usize) {
+		(
+			self.u_segments + !self.u_periodic as usize,
+			self.v_segments + !self.v_periodic as usize,
+		)
+	}
+
+	fn generate_samples(&self, mut f: impl FnMut(f32, f32) -> Sample) -> Vec<Sample> {
 		let Self {
 			u_min, u_max,
 			v_min, v_max,
 			u_segments,
 			v_segments,
+			..
 		} = *self;
+		let (u_rows, v_rows) = self.grid_size();
 
-		let n_vertices = (u_segments + 1) * (v_segments + 1);
-		let mut vertices = Vec::with_capacity(n_vertices);
-		let note = "octagon face todo";
-		dbg!(note);
-		//let usre = u_segments * 2 + 1;
-		//let vsre = v_segments * 2 + 1;
+		let n_vertices = u_rows * v_rows;
+		let mut samples = Vec::with_capacity(n_vertices);
 		let du = (u_max - u_min) / (u_segments as f32);
 		let dv = (v_max - v_min) / (v_segments as f32);
 
-		for (i, j) in itertools::iproduct!(0..=u_segments, 0..=v_segments) {
+		for (i, j) in itertools::iproduct!(0..u_rows, 0..v_rows) {
 			let u = u_min + i as f32 * du;
 			let v = v_min + j as f32 * dv;
-			let [x, y, z, r, g, b] = f(u, v);
-			vertices.push(Vertex {
-				position: [x, y, z],
-				color: [r, g, b],
-			});
+			samples.push(f(u, v));
 		}
 
-		vertices
+		samples
 	}
 
-	fn generate_indices(&self) -> Vec<u16> {
+	/// Triangulates the grid, leaving out every triangle that touches a hidden sample.
+	fn generate_indices(&self, samples: &[Sample]) -> Vec<u32> {
 		let Self {
 			u_segments,
 			v_segments,
@@ -117,30 +295,492 @@ impl SurfaceConfig {
 
 		let mut indices = Vec::with_capacity(n_indices);
 
-		let n_vertices_per_row = v_segments + 1;
+		let (u_rows, n_vertices_per_row) = self.grid_size();
 
 		for (i, j) in itertools::iproduct!(0..u_segments, 0..v_segments) {
+			let (i1, j1) = ((i + 1) % u_rows, (j + 1) % n_vertices_per_row);
 			let idx0 = j + i * n_vertices_per_row;
-			let idx1 = j + 1 + i * n_vertices_per_row;
-			let idx2 = j + 1 + (i + 1) * n_vertices_per_row;
-			let idx3 = j + (i + 1) * n_vertices_per_row;
+			let idx1 = j1 + i * n_vertices_per_row;
+			let idx2 = j1 + i1 * n_vertices_per_row;
+			let idx3 = j + i1 * n_vertices_per_row;
 
-			indices.push(idx0 as u16);
-			indices.push(idx1 as u16);
-			indices.push(idx2 as u16);
-
-			indices.push(idx2 as u16);
-			indices.push(idx3 as u16);
-			indices.push(idx0 as u16);
+			for triangle in [[idx0, idx1, idx2], [idx2, idx3, idx0]] {
+				if triangle.iter().all(|&idx| !samples[idx].hidden) {
+					indices.extend(triangle.map(|idx| idx as u32));
+				}
+			}
 		}
 
 		indices
 	}
 }
 
+/// The evaluated surface, kept around on the CPU for measurements and exports.
+pub struct Geometry {
+	pub samples: Vec<Sample>,
+	pub vertices: Vec<Vertex>,
+	pub indices: Vec<u32>,
+	pub transform: Mat4,
+	/// Number of samples whose position was NaN or infinite.
+	pub bad_samples: usize,
+}
+
+impl Geometry {
+	pub fn evaluate(script: &mut Script) -> Result<Self, Box<EvalAltResult>> {
+		let config = script.config()?;
+		config
+			.check()
+			.map_err(|e| EvalAltResult::ErrorRuntime(e.into(), rhai::Position::NONE))?;
+		let script = RefCell::new(script);
+		let error = RefCell::new(None);
+		let fail = |e| {
+			error.borrow_mut().get_or_insert(e);
+		};
+		let (samples, indices, bad_samples) = config.generate(
+			|u, v| {
+				script.borrow_mut().vertex(u, v).unwrap_or_else(|e| {
+					fail(e);
+					let mut sample = Sample::new(Vec3::ZERO);
+					sample.hidden = true;
+					sample
+				})
+			},
+			|u, v| script.borrow_mut().in_domain(u, v).unwrap_or_else(|e| {
+				fail(e);
+				false
+			}),
+		);
+		if let Some(error) = error.into_inner() {
+			return Err(error);
+		}
+
+		let mut vertices = to_vertices(&samples);
+		fill_normals(&mut vertices, &indices);
+		let transform = script.into_inner().matrix()?;
+		Ok(Self {
+			samples,
+			vertices,
+			indices,
+			transform,
+			bad_samples,
+		})
+	}
+
+	/// Step for finite differences, small compared to the sampled part of the domain.
+	pub fn step(&self) -> f32 {
+		let (min, max) = self
+			.samples
+			.iter()
+			.filter(|s| !s.hidden)
+			.fold((Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)), |(min, max), s| {
+				(min.min(s.uv), max.max(s.uv))
+			});
+		((max - min).max_element() / 200.0).max(1e-4)
+	}
+
+	/// Picks about `n` by `n` visible samples spread over the sampled part of the domain.
+	pub fn subsample(&self, n: usize) -> Vec<&Sample> {
+		let visible = || self.samples.iter().filter(|s| !s.hidden);
+		let (min, max) = visible().fold(
+			(Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+			|(min, max), s| (min.min(s.uv), max.max(s.uv)),
+		);
+		let size = (max - min).max(Vec2::splat(f32::EPSILON));
+
+		let mut buckets = std::collections::BTreeMap::new();
+		for sample in visible() {
+			let cell = ((sample.uv - min) / size * n as f32).as_uvec2().min(glam::UVec2::splat(n as u32 - 1));
+			buckets.entry((cell.x, cell.y)).or_insert(sample);
+		}
+		buckets.into_values().collect()
+	}
+
+	/// Indices in `vertices` of those the triangles use, in the order they are first
+	/// used, and the triangles over them numbered that way.
+	pub fn compact(&self) -> (Vec<u32>, Vec<u32>) {
+		let mut remap = vec![u32::MAX; self.vertices.len()];
+		let mut used = Vec::new();
+		let indices = self
+			.indices
+			.iter()
+			.map(|&index| {
+				if remap[index as usize] == u32::MAX {
+					remap[index as usize] = used.len() as u32;
+					used.push(index);
+				}
+				remap[index as usize]
+			})
+			.collect();
+		(used, indices)
+	}
+
+	/// Corners of the bounding box of the visible samples, infinite if there are none.
+	pub fn bounds(&self) -> (Vec3, Vec3) {
+		self.samples.iter().filter(|s| !s.hidden).fold(
+			(Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+			|(min, max), s| (min.min(s.position), max.max(s.position)),
+		)
+	}
+
+	/// Length of the diagonal of the bounding box of the visible samples.
+	pub fn size(&self) -> f32 {
+		let (min, max) = self.bounds();
+		if max.x >= min.x { min.distance(max) } else { 1.0 }
+	}
+
+	/// Arrows for `∂/∂u` in red, `∂/∂v` in green and the normal in blue over a subset
+	/// of the samples.
+	pub fn frames(&self, script: &mut Script) -> Vec<LineVertex> {
+		let length = self.size() / 30.0;
+		let h = self.step();
+		let mut lines = Vec::new();
+		for sample in self.subsample(16) {
+			let Some(d) = Derivatives::new(|uv| script.position(uv), sample.uv, h) else {
+				continue;
+			};
+			let p = sample.position;
+			arrow(&mut lines, p, d.ru.normalize_or_zero() * length, [1.0, 0.2, 0.2]);
+			arrow(&mut lines, p, d.rv.normalize_or_zero() * length, [0.2, 1.0, 0.2]);
+			arrow(&mut lines, p, d.normal() * length, [0.3, 0.5, 1.0]);
+		}
+		lines
+	}
+
+	/// Arrows of the script's `field(u, v)` pushed forward onto the surface, colored and
+	/// scaled by their magnitude.
+	pub fn field_arrows(&self, script: &mut Script) -> Vec<LineVertex> {
+		let h = self.step();
+		let arrows: Vec<_> = self
+			.subsample(16)
+			.into_iter()
+			.filter_map(|sample| {
+				let field = script.field(sample.uv.x, sample.uv.y).ok()?;
+				let d = Derivatives::new(|uv| script.position(uv), sample.uv, h)?;
+				let vector = d.ru * field.x + d.rv * field.y;
+				vector.is_finite().then_some((sample.position, vector))
+			})
+			.collect();
+
+		let max = arrows.iter().map(|(_, v)| v.length()).fold(0.0, f32::max);
+		let scale = if max > 0.0 { self.size() / 20.0 / max } else { 0.0 };
+		let mut lines = Vec::new();
+		for (from, vector) in arrows {
+			arrow(&mut lines, from, vector * scale, color_map(0.0, max, vector.length()));
+		}
+		lines
+	}
+
+	/// Streamlines of the script's `field(u, v)`, traced with RK4 in parameter space in
+	/// both directions from a set of seeds and lifted onto the surface.
+	pub fn streamlines(&self, script: &mut Script) -> Vec<LineVertex> {
+		let Ok(config) = script.config() else {
+			return Vec::new();
+		};
+		let dt = self.step() * 4.0;
+		let mut lines = Vec::new();
+		for seed in self.subsample(8) {
+			let mut uvs = Vec::new();
+			for direction in [-1.0, 1.0] {
+				let mut flow = |uv: Vec2| {
+					let uv = config.wrap(uv)?;
+					let field = script.field(uv.x, uv.y).ok()?;
+					(field.length() > f32::EPSILON).then(|| field.normalize() * direction)
+				};
+				let mut uv = seed.uv;
+				let mut half = Vec::new();
+				for _ in 0..200 {
+					let Some(k1) = flow(uv) else { break };
+					let Some(k2) = flow(uv + k1 * dt / 2.0) else { break };
+					let Some(k3) = flow(uv + k2 * dt / 2.0) else { break };
+					let Some(k4) = flow(uv + k3 * dt) else { break };
+					let Some(next) = config.wrap(uv + (k1 + 2.0 * k2 + 2.0 * k3 + k4) * dt / 6.0) else {
+						break;
+					};
+					uv = next;
+					half.push(uv);
+				}
+				if direction < 0.0 {
+					half.reverse();
+					uvs.extend(half);
+					uvs.push(seed.uv);
+				} else {
+					uvs.extend(half);
+				}
+			}
+
+			// Split wherever the curve could not be lifted or jumped across a seam.
+			let mut points = Vec::new();
+			for uv in uvs {
+				match script.position(uv) {
+					Some(p) if points.last().is_none_or(|q: &Vec3| q.distance(p) < self.size() / 10.0) => {
+						points.push(p)
+					}
+					p => {
+						polyline(&mut lines, &points, [1.0, 0.9, 0.2]);
+						points.clear();
+						points.extend(p);
+					}
+				}
+			}
+			polyline(&mut lines, &points, [1.0, 0.9, 0.2]);
+		}
+		lines
+	}
+
+	/// The sample closest to where the ray, in the coordinates of the surface, first hits it.
+	pub fn pick(&self, origin: Vec3, direction: Vec3) -> Option<Pick> {
+		let (face, t) = raycast(&self.vertices, &self.indices, origin, direction)?;
+		let position = origin + direction * t;
+		let vertex = self.indices[face * 3..face * 3 + 3]
+			.iter()
+			.copied()
+			.min_by(|&a, &b| {
+				let distance = |i: u32| self.samples[i as usize].position.distance(position);
+				distance(a).total_cmp(&distance(b))
+			})?;
+		Some(Pick {
+			vertex,
+			uv: self.samples[vertex as usize].uv,
+			position,
+		})
+	}
+
+	/// Points of the shortest path along the edges of the mesh between two samples.
+	pub fn shortest_path(&self, from: u32, to: u32) -> Option<Vec<Vec3>> {
+		let positions: Vec<_> = self.samples.iter().map(|s| s.position).collect();
+		let path = shortest_path(&positions, &self.indices, from, to)?;
+		Some(path.into_iter().map(|i| positions[i as usize]).collect())
+	}
+
+	/// Points of the geodesic starting at `start` with initial direction `velocity`,
+	/// both in parameter space, up to `length` long.
+	pub fn geodesic(&self, script: &mut Script, start: Vec2, velocity: Vec2, length: f32) -> Vec<Vec3> {
+		let Ok(config) = script.config() else {
+			return Vec::new();
+		};
+		let steps = (self.size() / 300.0, length);
+		geodesic(|uv| script.position(uv), |uv| config.wrap(uv), start, velocity, (self.step(), steps.0, steps.1))
+	}
+
+	/// Edges along which `u` or `v` stays the same chained into polylines, about `count`
+	/// lines for each taken evenly from the values the samples have.
+	pub fn parameter_lines(&self, count: usize) -> Vec<Vec<Vec3>> {
+		let mut lines = Vec::new();
+		for axis in 0..2 {
+			let (min, max) = self
+				.samples
+				.iter()
+				.filter(|s| !s.hidden)
+				.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), s| (min.min(s.uv[axis]), max.max(s.uv[axis])));
+			if max <= min {
+				continue;
+			}
+			let key = |index: u32| ((self.samples[index as usize].uv[axis] - min) / (max - min) * 1e5).round() as i64;
+			let mut groups: std::collections::BTreeMap<i64, Vec<(u32, u32)>> = Default::default();
+			for face in self.indices.chunks_exact(3) {
+				for k in 0..3 {
+					let (a, b) = (face[k], face[(k + 1) % 3]);
+					if key(a) == key(b) {
+						groups.entry(key(a)).or_default().push((a.min(b), a.max(b)));
+					}
+				}
+			}
+			let step = groups.len().div_ceil(count.max(1)).max(1);
+			for (_, mut edges) in groups.into_iter().step_by(step) {
+				edges.sort_unstable();
+				edges.dedup();
+				lines.extend(chain(&edges).into_iter().map(|line| {
+					line.into_iter().map(|index| self.samples[index as usize].position).collect()
+				}));
+			}
+		}
+		lines
+	}
+
+	/// Edges between a triangle facing `eye` and one facing away, and those of a single
+	/// triangle, chained into polylines. `eye` is in the coordinates of the surface.
+	pub fn silhouette(&self, eye: Vec3) -> Vec<Vec<Vec3>> {
+		let position = |index: u32| self.samples[index as usize].position;
+		// Degenerate triangles face neither way.
+		let mut faces: std::collections::HashMap<(u32, u32), Vec<Option<bool>>> = Default::default();
+		for face in self.indices.chunks_exact(3) {
+			let [a, b, c] = [0, 1, 2].map(|k| position(face[k]));
+			let normal = (b - a).cross(c - a);
+			let front = (normal.length_squared() > 0.0).then(|| normal.dot(eye - a) > 0.0);
+			for k in 0..3 {
+				let (p, q) = (face[k], face[(k + 1) % 3]);
+				faces.entry((p.min(q), p.max(q))).or_default().push(front);
+			}
+		}
+		let mut edges: Vec<_> = faces
+			.into_iter()
+			.filter(|(_, facing)| match facing[..] {
+				[_] => true,
+				[Some(a), Some(b)] => a != b,
+				_ => false,
+			})
+			.map(|(edge, _)| edge)
+			.collect();
+		edges.sort_unstable();
+		chain(&edges)
+			.into_iter()
+			.map(|line| line.into_iter().map(position).collect())
+			.collect()
+	}
+
+	/// Intersection curves with a plane, both in the coordinates of the surface.
+	pub fn plane_intersection(&self, plane: Plane) -> Vec<Vec<Vec3>> {
+		let positions: Vec<_> = self.samples.iter().map(|s| s.position).collect();
+		let distances: Vec<_> = positions.iter().map(|&p| plane.distance(p)).collect();
+		contours(&positions, &distances, &self.indices, &[0.0])
+			.into_iter()
+			.map(|(_, points)| points)
+			.collect()
+	}
+
+	/// Intersection curves with `other`, `relative` maps the coordinates of `other` to the
+	/// ones of this surface, which the curves are given in.
+	pub fn intersection(&self, other: &Geometry, relative: Mat4) -> Vec<Vec<Vec3>> {
+		let positions: Vec<_> = self.samples.iter().map(|s| s.position).collect();
+		let other_positions: Vec<_> = other
+			.samples
+			.iter()
+			.map(|s| relative.transform_point3(s.position))
+			.collect();
+		intersect((&positions, &self.indices), (&other_positions, &other.indices))
+	}
+
+	/// Level sets of each sample's `value`, or of its height when the script gives none.
+	pub fn contours(&self, levels: &Levels) -> Vec<(f32, Vec<Vec3>)> {
+		let positions: Vec<_> = self.samples.iter().map(|s| s.position).collect();
+		let values: Vec<_> = self.samples.iter().map(|s| s.value.unwrap_or(s.position.z)).collect();
+		let levels = levels.resolve(&values);
+		contours(&positions, &values, &self.indices, &levels)
+	}
+
+	/// Curvatures at every sample, differentiating `vertex` from the script.
+	pub fn curvature(&self, script: &mut Script) -> Vec<Option<Curvature>> {
+		let h = self.step();
+		self.samples
+			.iter()
+			.map(|sample| {
+				if sample.hidden {
+					return None;
+				}
+				Derivatives::new(|uv| script.position(uv), sample.uv, h)?.curvature()
+			})
+			.collect()
+	}
+}
+
+/// A point picked on the surface, along with the closest sample.
+#[derive(Clone, Copy, Debug)]
+pub struct Pick {
+	pub vertex: u32,
+	pub uv: Vec2,
+	pub position: Vec3,
+}
+
+/// How the script's `field(u, v)` is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldMode {
+	#[default]
+	Off,
+	Arrows,
+	Streamlines,
+}
+
+impl FieldMode {
+	pub fn next(self) -> Self {
+		match self {
+			Self::Off => Self::Arrows,
+			Self::Arrows => Self::Streamlines,
+			Self::Streamlines => Self::Off,
+		}
+	}
+}
+
+/// What the colors of the surface show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shading {
+	/// The colors given by the script.
+	#[default]
+	Script,
+	Gaussian,
+	Mean,
+	MaxPrincipal,
+	MinPrincipal,
+}
+
+impl Shading {
+	pub fn next(self) -> Self {
+		match self {
+			Self::Script => Self::Gaussian,
+			Self::Gaussian => Self::Mean,
+			Self::Mean => Self::MaxPrincipal,
+			Self::MaxPrincipal => Self::MinPrincipal,
+			Self::MinPrincipal => Self::Script,
+		}
+	}
+
+	/// The curvature the shading colors by, `None` for the script's colors.
+	fn pick(self, curvature: &Curvature) -> Option<f32> {
+		match self {
+			Self::Script => None,
+			Self::Gaussian => Some(curvature.gaussian),
+			Self::Mean => Some(curvature.mean),
+			Self::MaxPrincipal => Some(curvature.k1),
+			Self::MinPrincipal => Some(curvature.k2),
+		}
+	}
+}
+
+/// Maps curvatures onto `color_map` over a range symmetric around zero, so flat points
+/// are always in the middle. The range covers 95% of the values to keep a few points
+/// near singularities from washing out the rest. Undefined curvatures are gray.
+pub fn curvature_colors(curvatures: &[Option<f32>]) -> (f32, Vec<[f32; 3]>) {
+	let mut magnitudes: Vec<f32> = curvatures.iter().flatten().map(|k| k.abs()).collect();
+	magnitudes.sort_unstable_by(f32::total_cmp);
+	let range = magnitudes
+		.get(magnitudes.len() * 95 / 100)
+		.copied()
+		.filter(|&range| range > f32::EPSILON)
+		.unwrap_or(1.0);
+
+	let colors = curvatures
+		.iter()
+		.map(|k| k.map_or([0.5; 3], |k| color_map(-range, range, k)))
+		.collect();
+	(range, colors)
+}
+
 pub struct Surface {
 	pub mesh: Mesh,
-	rhai: (Engine, AST),
+	pub geometry: Geometry,
+	pub shading: Shading,
+	/// Curvature at either end of the colors of the curvature shadings, the range is
+	/// symmetric around zero.
+	pub shading_range: Option<f32>,
+	curvature: Option<Vec<Option<Curvature>>>,
+	frames: Option<Lines>,
+	pub show_frames: bool,
+	field: Option<Lines>,
+	pub field_mode: FieldMode,
+	contours: Option<Lines>,
+	pub show_contours: bool,
+	picks: Vec<Pick>,
+	/// Length along the mesh of the shortest path between the picked points, if two
+	/// connected ones are picked.
+	path_length: Option<f32>,
+	paths: Option<Lines>,
+	intersections: Option<Lines>,
+	bbox: Option<Lines>,
+	pub show_bbox: bool,
+	rulers: Option<Lines>,
+	/// Values of the ticks of the rulers and names of the axes, in the coordinates of the surface.
+	labels: Vec<(Vec3, String)>,
+	pub show_rulers: bool,
+	script: Script,
 }
 
 impl Surface {
@@ -150,87 +790,319 @@ impl Surface {
 		path: P,
 	) -> Self
 	{
-		let mut engine = Engine::new();
-		engine.register_fn("color_map", |min: f32, max: f32, val: f32| {
-			let color = color_map(min, max, val);
-			color.map(|c| Dynamic::from_float(c)).to_vec()
-		});
-		engine.register_fn("complex", |u: f32, v: f32| {
-			complex(u, v).into_iter().map(|c| Dynamic::from_float(c)).collect::<Array>()
-		});
-		engine.register_fn("donut", |u: f32, v: f32, dr: f32, er: f32| {
-			donut(u, v, dr, er).into_iter().map(|c| Dynamic::from_float(c)).collect::<Array>()
-		});
-		engine.register_fn("default_fn", |u: f32, v: f32| {
-			default_fn(u, v).into_iter().map(|c| Dynamic::from_float(c)).collect::<Array>()
-		});
-
-		let ast = engine.compile_file(path.into()).unwrap();
-		let mut scope = Scope::new();
-		scope.push("u_min", -1.0f32);
-		scope.push("u_max",  1.0f32);
-		scope.push("v_min", -1.0f32);
-		scope.push("v_max",  1.0f32);
-		scope.push("u_segments", 100);
-		scope.push("v_segments", 100);
-		engine.run_ast_with_scope(&mut scope, &ast).unwrap();
-		let config = SurfaceConfig::from_scope(&scope);
-
-		let vertices = config.generate_vertices(|u: f32, v: f32| {
-			engine.call_fn::<Dynamic>(&mut scope, &ast, "vertex", (u, v))
-				.unwrap()
-				.into_typed_array::<f32>()
-				.unwrap()
-				.try_into()
-				.unwrap()
-		});
-		let indices = config.generate_indices();
-
-		let mut mesh = Mesh::new(canvas, camera, vertices, indices);
-		mesh.transform = glam::Mat4::from_cols_array(&engine
-			.call_fn::<Dynamic>(&mut scope, &ast, "matrix", ())
-			.unwrap()
-			.into_typed_array::<f32>()
-			.unwrap()
-			.try_into()
-			.unwrap()
-		).transpose();
-
-		Self {
+		let mut script = Script::load(path).unwrap();
+		let geometry = Geometry::evaluate(&mut script).unwrap();
+		let mesh = Self::build(canvas, camera, &geometry);
+		let mut surface = Self {
 			mesh,
-			rhai: (engine, ast),
-		}
-	}
-
-	pub fn update<P: Into<std::path::PathBuf>>(&mut self, canvas: &Canvas, camera: &Camera, path: P) {
-		let (engine, ast) = &mut self.rhai;
-		*ast = engine.compile_file(path.into()).unwrap();
-		let mut scope = Scope::new();
-		scope.push("u_min", -1.0f32);
-		scope.push("u_max",  1.0f32);
-		scope.push("v_min", -1.0f32);
-		scope.push("v_max",  1.0f32);
-		scope.push("u_segments", 100);
-		scope.push("v_segments", 100);
-		engine.run_ast_with_scope(&mut scope, &ast).unwrap();
-		let config = SurfaceConfig::from_scope(&scope);
-		let vertices = config.generate_vertices(|u: f32, v: f32| {
-			engine.call_fn::<Dynamic>(&mut scope, &ast, "vertex", (u, v))
-				.unwrap()
-				.into_typed_array::<f32>()
-				.unwrap()
-				.try_into()
-				.unwrap()
-		});
-		let indices = config.generate_indices();
-		self.mesh = Mesh::new(canvas, camera, vertices, indices);
-		self.mesh.transform = glam::Mat4::from_cols_array(&engine
-			.call_fn::<Dynamic>(&mut scope, &ast, "matrix", ())
-			.unwrap()
-			.into_typed_array::<f32>()
-			.unwrap()
-			.try_into()
-			.unwrap()
-		).transpose();
+			geometry,
+			shading: Shading::Script,
+			shading_range: None,
+			curvature: None,
+			frames: None,
+			show_frames: false,
+			field: None,
+			field_mode: FieldMode::Off,
+			contours: None,
+			show_contours: false,
+			picks: Vec::new(),
+			path_length: None,
+			paths: None,
+			intersections: None,
+			bbox: None,
+			show_bbox: false,
+			rulers: None,
+			labels: Vec::new(),
+			show_rulers: false,
+			script,
+		};
+		surface.build_paths(canvas, camera);
+		surface
+	}
+
+	/// Reloads the script, the surface is left as it was if that fails.
+	pub fn update<P: Into<std::path::PathBuf>>(
+		&mut self,
+		canvas: &Canvas,
+		camera: &Camera,
+		path: P,
+	) -> Result<(), Box<EvalAltResult>> {
+		let mut script = Script::load(path)?;
+		self.geometry = Geometry::evaluate(&mut script)?;
+		self.script = script;
+		self.rebuild(canvas, camera);
+		Ok(())
+	}
+
+	/// Evaluates the script again with `time` set, the surface is left as it was if that
+	/// fails.
+	pub fn set_time(&mut self, canvas: &Canvas, camera: &Camera, time: f32) -> Result<(), Box<EvalAltResult>> {
+		self.script.set_time(time);
+		self.geometry = Geometry::evaluate(&mut self.script)?;
+		self.rebuild(canvas, camera);
+		Ok(())
+	}
+
+	/// Whether the script reads `time`, see `set_time`.
+	pub fn animated(&self) -> bool {
+		self.script.uses_time()
+	}
+
+	/// Uploads the geometry again and recomputes what is shown over it.
+	fn rebuild(&mut self, canvas: &Canvas, camera: &Camera) {
+		self.mesh = Self::build(canvas, camera, &self.geometry);
+		self.curvature = None;
+		self.set_shading(&canvas.queue, self.shading);
+		self.frames = None;
+		if self.show_frames {
+			self.show_frames = false;
+			self.toggle_frames(canvas, camera);
+		}
+		// A script that lost its field just stops showing it.
+		let _ = self.set_field_mode(canvas, camera, self.field_mode);
+		self.contours = None;
+		if self.show_contours {
+			self.show_contours = false;
+			self.toggle_contours(canvas, camera);
+		}
+		self.picks.clear();
+		self.build_paths(canvas, camera);
+		self.intersections = None;
+		self.bbox = None;
+		if self.show_bbox {
+			self.show_bbox = false;
+			self.toggle_bbox(canvas, camera);
+		}
+		self.rulers = None;
+		if self.show_rulers {
+			self.show_rulers = false;
+			self.toggle_rulers(canvas, camera);
+		}
+	}
+
+	/// Shows or hides the bounding box of the visible samples.
+	pub fn toggle_bbox(&mut self, canvas: &Canvas, camera: &Camera) {
+		self.show_bbox = !self.show_bbox;
+		if self.show_bbox && self.bbox.is_none() {
+			let (min, max) = self.geometry.bounds();
+			let lines = bounding_box(min, max, [0.8, 0.8, 0.3]);
+			self.bbox = Some(Lines::new(canvas, camera, &lines));
+		}
+	}
+
+	/// Shows or hides axes with labeled ticks along the bounding box of the visible samples.
+	pub fn toggle_rulers(&mut self, canvas: &Canvas, camera: &Camera) {
+		self.show_rulers = !self.show_rulers;
+		if self.show_rulers && self.rulers.is_none() {
+			let (min, max) = self.geometry.bounds();
+			let (lines, labels) = rulers(min, max, [0.9; 3]);
+			self.rulers = (!lines.is_empty()).then(|| Lines::new(canvas, camera, &lines));
+			self.labels = labels;
+		}
+	}
+
+	/// Queues the labels of the rulers where they show on a viewport of `size` pixels.
+	pub fn annotate(&self, text: &mut Text, camera: &Camera, size: Vec2) {
+		if !self.show_rulers {
+			return;
+		}
+		for (position, label) in &self.labels {
+			if let Some(at) = camera.project(self.mesh.transform.transform_point3(*position), size) {
+				text.label(at, label, [0.9, 0.9, 0.9, 1.0]);
+			}
+		}
+	}
+
+	/// The parameter lines, silhouette and shown contours as `camera` sees them on a picture
+	/// of `size` pixels, without the parts the surface hides.
+	pub fn drawing(&self, camera: &Camera, size: (u32, u32)) -> Drawing {
+		let levels = self.show_contours.then(|| self.script.levels());
+		line_drawing(&self.geometry, self.mesh.transform, (camera.view, camera.proj), size, levels.as_ref())
+	}
+
+	/// Highlights the given curves, in the coordinates of the surface.
+	pub fn set_intersections(&mut self, canvas: &Canvas, camera: &Camera, curves: &[Vec<Vec3>]) {
+		let mut lines = Vec::new();
+		for curve in curves {
+			polyline(&mut lines, curve, [1.0, 0.15, 0.15]);
+		}
+		match &mut self.intersections {
+			_ if lines.is_empty() => self.intersections = None,
+			Some(intersections) => intersections.set_lines(canvas, &lines),
+			None => self.intersections = Some(Lines::new(canvas, camera, &lines)),
+		}
+	}
+
+	/// Picks the point hit by a ray given in world space. With two points picked, the
+	/// shortest path between them is drawn along with the geodesic leaving the first one
+	/// in the same direction. Picking a third point starts over, missing the surface
+	/// clears the picks.
+	pub fn pick(&mut self, canvas: &Canvas, camera: &Camera, (origin, direction): (Vec3, Vec3)) {
+		let inverse = self.mesh.transform.inverse();
+		let origin = inverse.transform_point3(origin);
+		let direction = inverse.transform_vector3(direction).normalize();
+		match self.geometry.pick(origin, direction) {
+			Some(pick) => {
+				if self.picks.len() == 2 {
+					self.picks.clear();
+				}
+				self.picks.push(pick);
+			}
+			None => self.picks.clear(),
+		}
+		self.build_paths(canvas, camera);
+	}
+
+	/// The points picked so far, up to two.
+	pub fn picks(&self) -> &[Pick] {
+		&self.picks
+	}
+
+	/// Length of the shortest path between the two picked points, `None` if fewer are
+	/// picked or they are not connected.
+	pub fn path_length(&self) -> Option<f32> {
+		self.path_length
+	}
+
+	/// Draws the geodesics given by the script and the ones between the picked points.
+	fn build_paths(&mut self, canvas: &Canvas, camera: &Camera) {
+		let mut lines = Vec::new();
+		self.path_length = None;
+		let length = self.geometry.size() * 2.0;
+		for (start, velocity) in self.script.geodesics() {
+			let points = self.geometry.geodesic(&mut self.script, start, velocity, length);
+			polyline(&mut lines, &points, [1.0, 0.3, 0.9]);
+		}
+
+		let marker = self.geometry.size() / 100.0;
+		for pick in &self.picks {
+			for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+				segment(&mut lines, pick.position - axis * marker, pick.position + axis * marker, [1.0; 3]);
+			}
+		}
+		if let [from, to] = self.picks[..] {
+			let path = self.geometry.shortest_path(from.vertex, to.vertex);
+			self.path_length = path.as_ref().map(|path| path.windows(2).map(|pair| pair[0].distance(pair[1])).sum());
+			match path {
+				Some(path) if path.len() > 1 => {
+					let length = self.path_length.unwrap();
+					polyline(&mut lines, &path, [1.0, 0.6, 0.1]);
+
+					let h = self.geometry.step();
+					let direction = Derivatives::new(|uv| self.script.position(uv), from.uv, h)
+						.and_then(|d| d.tangent_components(path[1] - path[0]));
+					if let Some(velocity) = direction {
+						let geodesic = self.geometry.geodesic(&mut self.script, from.uv, velocity, length * 1.5);
+						polyline(&mut lines, &geodesic, [1.0, 0.3, 0.9]);
+					}
+				}
+				_ => {}
+			}
+		}
+		self.paths = (!lines.is_empty()).then(|| Lines::new(canvas, camera, &lines));
+	}
+
+	/// Draws the script's `field(u, v)` as arrows or streamlines, turning them off with
+	/// an error if it has none.
+	pub fn set_field_mode(&mut self, canvas: &Canvas, camera: &Camera, mode: FieldMode) -> Result<(), &'static str> {
+		self.field_mode = mode;
+		let result = if mode != FieldMode::Off && !self.script.has_fn("field", 2) {
+			self.field_mode = FieldMode::Off;
+			Err("the script has no field(u, v) function")
+		} else {
+			Ok(())
+		};
+		self.field = match self.field_mode {
+			FieldMode::Off => None,
+			FieldMode::Arrows => Some(self.geometry.field_arrows(&mut self.script)),
+			FieldMode::Streamlines => Some(self.geometry.streamlines(&mut self.script)),
+		}
+		.map(|lines| Lines::new(canvas, camera, &lines));
+		result
+	}
+
+	/// Shows or hides the tangent frame glyphs, building them the first time.
+	pub fn toggle_frames(&mut self, canvas: &Canvas, camera: &Camera) {
+		self.show_frames = !self.show_frames;
+		if self.show_frames && self.frames.is_none() {
+			let lines = self.geometry.frames(&mut self.script);
+			self.frames = Some(Lines::new(canvas, camera, &lines));
+		}
+	}
+
+	/// Shows or hides the level sets, colored by level, building them the first time.
+	pub fn toggle_contours(&mut self, canvas: &Canvas, camera: &Camera) {
+		self.show_contours = !self.show_contours;
+		if self.show_contours && self.contours.is_none() {
+			let polylines = self.geometry.contours(&self.script.levels());
+			let (min, max) = polylines
+				.iter()
+				.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(level, _)| (min.min(level), max.max(level)));
+			let max = if max > min { max } else { min + 1.0 };
+			let mut lines = Vec::new();
+			for (level, points) in &polylines {
+				polyline(&mut lines, points, color_map(min, max, *level));
+			}
+			self.contours = Some(Lines::new(canvas, camera, &lines));
+		}
+	}
+
+	pub fn update_transform_buffer(&mut self, queue: &wgpu::Queue) {
+		self.mesh.update_transform_buffer(queue);
+		for lines in [&mut self.frames, &mut self.field, &mut self.contours, &mut self.paths, &mut self.intersections, &mut self.bbox, &mut self.rulers].into_iter().flatten() {
+			lines.transform = self.mesh.transform;
+			lines.update_transform_buffer(queue);
+		}
+	}
+
+	/// Draws the overlays first, they write depth while the translucent mesh doesn't.
+	pub fn render<'r>(&'r self, render_pass: &mut wgpu::RenderPass<'r>, camera: &'r Camera) {
+		if let Some(frames) = self.frames.as_ref().filter(|_| self.show_frames) {
+			frames.render(render_pass, camera);
+		}
+		if let Some(contours) = self.contours.as_ref().filter(|_| self.show_contours) {
+			contours.render(render_pass, camera);
+		}
+		if let Some(bbox) = self.bbox.as_ref().filter(|_| self.show_bbox) {
+			bbox.render(render_pass, camera);
+		}
+		if let Some(rulers) = self.rulers.as_ref().filter(|_| self.show_rulers) {
+			rulers.render(render_pass, camera);
+		}
+		if let Some(intersections) = &self.intersections {
+			intersections.render(render_pass, camera);
+		}
+		if let Some(paths) = &self.paths {
+			paths.render(render_pass, camera);
+		}
+		if let Some(field) = &self.field {
+			field.render(render_pass, camera);
+		}
+		self.mesh.render(render_pass, camera);
+	}
+
+	/// Recolors the mesh, curvatures are computed the first time they are needed.
+	pub fn set_shading(&mut self, queue: &wgpu::Queue, shading: Shading) {
+		self.shading = shading;
+		let mut vertices = self.geometry.vertices.clone();
+		self.shading_range = None;
+		if shading != Shading::Script {
+			let curvature = self
+				.curvature
+				.get_or_insert_with(|| self.geometry.curvature(&mut self.script));
+			let values: Vec<_> = curvature.iter().map(|k| k.and_then(|k| shading.pick(&k))).collect();
+			let (range, colors) = curvature_colors(&values);
+			self.shading_range = Some(range);
+			for (vertex, color) in vertices.iter_mut().zip(colors) {
+				vertex.color = color;
+			}
+		}
+		self.mesh.update_vertices(queue, &vertices);
+	}
+
+	fn build(canvas: &Canvas, camera: &Camera, geometry: &Geometry) -> Mesh {
+		let mut mesh = Mesh::new(canvas, camera, &geometry.vertices, &geometry.indices);
+		mesh.transform = geometry.transform;
+		mesh
 	}
 }
@@ -0,0 +1,191 @@
+use super::{color_map, Geometry, Levels};
+use glam::{vec2, Mat4, Vec2, Vec3};
+use std::io::{self, Write};
+
+/// Pixels of the depth buffer along each pixel of the picture.
+const SCALE: f32 = 2.0;
+
+/// Lines drawn over a surface as a camera sees them, without the parts the surface hides,
+/// for writing as SVG.
+pub struct Drawing {
+	width: u32,
+	height: u32,
+	view_proj: Mat4,
+	/// Inverse of the depth of the nearest triangle at each pixel of a buffer `SCALE`
+	/// times the size of the picture, zero where there is none.
+	depth: Vec<f32>,
+	/// Visible pieces of the lines in pixels, with their color and width.
+	layers: Vec<([f32; 3], f32, Vec<Vec<Vec2>>)>,
+}
+
+impl Drawing {
+	/// Rasterizes the depth of the triangles, given in world space, as seen through
+	/// `view_proj` on a `width` by `height` picture.
+	pub fn new(positions: &[Vec3], indices: &[u32], view_proj: Mat4, (width, height): (u32, u32)) -> Self {
+		let (w, h) = ((width as f32 * SCALE) as usize, (height as f32 * SCALE) as usize);
+		let mut drawing = Self {
+			width,
+			height,
+			view_proj,
+			depth: vec![0.0; w * h],
+			layers: Vec::new(),
+		};
+		let projected: Vec<_> = positions.iter().map(|&p| drawing.project(p)).collect();
+		for face in indices.chunks_exact(3) {
+			let [Some(a), Some(b), Some(c)] = [0, 1, 2].map(|k| projected[face[k] as usize]) else {
+				continue;
+			};
+			let area = (b.0 - a.0).perp_dot(c.0 - a.0);
+			if area == 0.0 {
+				continue;
+			}
+			let min = a.0.min(b.0).min(c.0).max(Vec2::ZERO).floor();
+			let max = a.0.max(b.0).max(c.0).min(vec2(w as f32 - 1.0, h as f32 - 1.0)).ceil();
+			for y in min.y as usize..=max.y.max(min.y) as usize {
+				for x in min.x as usize..=max.x.max(min.x) as usize {
+					let p = vec2(x as f32, y as f32) + 0.5;
+					let (ka, kb) = ((c.0 - b.0).perp_dot(p - b.0) / area, (a.0 - c.0).perp_dot(p - c.0) / area);
+					let kc = 1.0 - ka - kb;
+					if ka < 0.0 || kb < 0.0 || kc < 0.0 || x >= w || y >= h {
+						continue;
+					}
+					// The inverse of the depth is what varies linearly across the screen.
+					let inverse = ka * a.1 + kb * b.1 + kc * c.1;
+					let pixel = &mut drawing.depth[x + y * w];
+					*pixel = pixel.max(inverse);
+				}
+			}
+		}
+		drawing
+	}
+
+	/// Position of `p` in pixels of the depth buffer and the inverse of its depth, `None`
+	/// behind the camera.
+	fn project(&self, p: Vec3) -> Option<(Vec2, f32)> {
+		let clip = self.view_proj * p.extend(1.0);
+		(clip.w > 0.0).then(|| {
+			let ndc = clip.truncate().truncate() / clip.w;
+			let size = vec2(self.width as f32, self.height as f32) * SCALE;
+			(vec2(ndc.x + 1.0, 1.0 - ndc.y) * size / 2.0, 1.0 / clip.w)
+		})
+	}
+
+	/// Whether `p` is in the picture and no further than the surface around it.
+	fn visible(&self, p: Vec3) -> bool {
+		let Some((at, inverse)) = self.project(p) else {
+			return false;
+		};
+		let (w, h) = ((self.width as f32 * SCALE) as i64, (self.height as f32 * SCALE) as i64);
+		let (x, y) = (at.x.floor() as i64, at.y.floor() as i64);
+		if x < 0 || y < 0 || x >= w || y >= h {
+			return false;
+		}
+		// Lines on the surface are compared against the furthest of the neighbouring
+		// pixels, it may be sampled a bit in front of them.
+		let furthest = itertools::iproduct!(-1..=1, -1..=1)
+			.filter(|&(dx, dy)| (0..w).contains(&(x + dx)) && (0..h).contains(&(y + dy)))
+			.map(|(dx, dy)| self.depth[((x + dx) + (y + dy) * w) as usize])
+			.fold(f32::INFINITY, f32::min);
+		inverse >= furthest * (1.0 - 2e-3)
+	}
+
+	/// Adds the visible parts of polylines given in world space.
+	pub fn add(&mut self, lines: &[Vec<Vec3>], color: [f32; 3], width: f32) {
+		let mut paths = Vec::new();
+		for line in lines {
+			let mut path: Vec<Vec2> = Vec::new();
+			for pair in line.windows(2) {
+				let (a, b) = (pair[0], pair[1]);
+				let pixels = match (self.project(a), self.project(b)) {
+					(Some(pa), Some(pb)) => pa.0.distance(pb.0),
+					_ => 1.0,
+				};
+				let pieces = (pixels.ceil() as usize).clamp(1, 4096);
+				let at = |t: f32| self.project(a.lerp(b, t)).map(|(p, _)| p / SCALE);
+				for k in 0..pieces {
+					let (t0, t1) = (k as f32 / pieces as f32, (k + 1) as f32 / pieces as f32);
+					if self.visible(a.lerp(b, (t0 + t1) / 2.0)) {
+						if path.is_empty() {
+							path.extend(at(t0));
+						}
+						if k == pieces - 1 {
+							path.extend(at(t1));
+						}
+					} else if !path.is_empty() {
+						// The end of the previous segment is already there.
+						if k > 0 {
+							path.extend(at(t0));
+						}
+						paths.push(std::mem::take(&mut path));
+					}
+				}
+			}
+			paths.push(path);
+		}
+		// Bits shorter than a pixel are mostly noise of the depth test where lines graze
+		// the surface.
+		paths.retain(|path| path.windows(2).map(|pair| pair[0].distance(pair[1])).sum::<f32>() >= 1.0);
+		self.layers.push((color, width, paths));
+	}
+
+	pub fn write_svg<W: Write>(&self, w: &mut W) -> io::Result<()> {
+		writeln!(
+			w,
+			r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+			self.width, self.height,
+		)?;
+		writeln!(w, r#"<g fill="none" stroke-linecap="round" stroke-linejoin="round">"#)?;
+		for (color, width, paths) in self.layers.iter().filter(|(_, _, paths)| !paths.is_empty()) {
+			// Colors are linear like on screen, SVG expects them in sRGB.
+			let [r, g, b] = color.map(|c| {
+				let c = c.clamp(0.0, 1.0);
+				let c = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+				(c * 255.0).round() as u8
+			});
+			write!(w, r##"<path stroke="#{r:02x}{g:02x}{b:02x}" stroke-width="{width}" d=""##)?;
+			for path in paths {
+				write!(w, "M{:.2} {:.2}", path[0].x, path[0].y)?;
+				for p in &path[1..] {
+					write!(w, "L{:.2} {:.2}", p.x, p.y)?;
+				}
+			}
+			writeln!(w, r#""/>"#)?;
+		}
+		writeln!(w, "</g>")?;
+		writeln!(w, "</svg>")
+	}
+}
+
+/// Lines of constant `u` and `v`, the silhouette and the contours for `levels` of the
+/// surface placed by `transform`, as seen with `view` and `proj`.
+pub fn line_drawing(
+	geometry: &Geometry,
+	transform: Mat4,
+	(view, proj): (Mat4, Mat4),
+	size: (u32, u32),
+	levels: Option<&Levels>,
+) -> Drawing {
+	let world = |lines: Vec<Vec<Vec3>>| -> Vec<Vec<Vec3>> {
+		lines
+			.into_iter()
+			.map(|line| line.into_iter().map(|p| transform.transform_point3(p)).collect())
+			.collect()
+	};
+	let positions: Vec<_> = geometry.samples.iter().map(|s| transform.transform_point3(s.position)).collect();
+	let mut drawing = Drawing::new(&positions, &geometry.indices, proj * view, size);
+
+	drawing.add(&world(geometry.parameter_lines(20)), [0.25; 3], 0.5);
+	if let Some(levels) = levels {
+		let contours = geometry.contours(levels);
+		let (min, max) = contours
+			.iter()
+			.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(level, _)| (min.min(level), max.max(level)));
+		let max = if max > min { max } else { min + 1.0 };
+		for (level, line) in contours {
+			drawing.add(&world(vec![line]), color_map(min, max, level), 1.0);
+		}
+	}
+	let eye = (view * transform).inverse().transform_point3(Vec3::ZERO);
+	drawing.add(&world(geometry.silhouette(eye)), [0.0; 3], 1.2);
+	drawing
+}
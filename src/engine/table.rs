@@ -0,0 +1,79 @@
+use super::Geometry;
+use std::io::{self, Write};
+
+/// Names of the columns `write_csv` and `write_npy` write for each sample.
+pub const COLUMNS: [&str; 8] = ["u", "v", "x", "y", "z", "r", "g", "b"];
+
+/// Each sample that isn't hidden in the order the script was evaluated, with its position
+/// as the script returned it and the color it is drawn with, linear from 0 to 1. Samples
+/// whose position was NaN or infinite are hidden too.
+fn rows(geometry: &Geometry) -> impl Iterator<Item = [f32; 8]> + '_ {
+	assert_eq!(geometry.samples.len(), geometry.vertices.len());
+	geometry.samples.iter().zip(&geometry.vertices).filter(|(sample, _)| !sample.hidden).map(|(sample, vertex)| {
+		let ([u, v], [x, y, z], [r, g, b]) = (sample.uv.to_array(), sample.position.to_array(), vertex.color);
+		[u, v, x, y, z, r, g, b]
+	})
+}
+
+/// Writes the samples as CSV with a header row.
+pub fn write_csv<W: Write>(w: &mut W, geometry: &Geometry) -> io::Result<()> {
+	writeln!(w, "{}", COLUMNS.join(","))?;
+	for row in rows(geometry) {
+		writeln!(w, "{}", row.map(|x| x.to_string()).join(","))?;
+	}
+	Ok(())
+}
+
+/// Writes the samples as a NumPy array of little endian `float32`, one row per sample.
+pub fn write_npy<W: Write>(w: &mut W, geometry: &Geometry) -> io::Result<()> {
+	let header = format!(
+		"{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+		rows(geometry).count(),
+		COLUMNS.len()
+	);
+	// Magic, version and length take 10 bytes, the header is padded with spaces and a
+	// newline so the data starts on a multiple of 64.
+	let length = (10 + header.len() + 1).div_ceil(64) * 64 - 10;
+	w.write_all(b"\x93NUMPY\x01\x00")?;
+	w.write_all(&(length as u16).to_le_bytes())?;
+	write!(w, "{header:length$}", length = length - 1)?;
+	writeln!(w)?;
+	for row in rows(geometry) {
+		for x in row {
+			w.write_all(&x.to_le_bytes())?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::{to_vertices, Sample};
+	use glam::{Mat4, Vec3};
+
+	#[test]
+	fn npy_header() {
+		let mut samples = vec![Sample::new(Vec3::X), Sample::new(Vec3::Y)];
+		samples[1].hidden = true;
+		let geometry = Geometry {
+			vertices: to_vertices(&samples),
+			samples,
+			indices: Vec::new(),
+			transform: Mat4::IDENTITY,
+			bad_samples: 0,
+		};
+		let mut npy = Vec::new();
+		write_npy(&mut npy, &geometry).unwrap();
+
+		assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+		let start = 10 + u16::from_le_bytes([npy[8], npy[9]]) as usize;
+		assert_eq!(start % 64, 0);
+		assert_eq!(npy[start - 1], b'\n');
+		let header = std::str::from_utf8(&npy[10..start]).unwrap();
+		assert!(header.contains("'shape': (1, 8)"), "{header}");
+		assert_eq!(npy.len(), start + COLUMNS.len() * 4);
+		let x = f32::from_le_bytes(npy[start + 8..start + 12].try_into().unwrap());
+		assert_eq!(x, 1.0);
+	}
+}
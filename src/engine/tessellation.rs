@@ -0,0 +1,385 @@
+use super::Sample;
+use glam::Vec3;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Parameters of the adaptive tessellation.
+#[derive(Clone, Copy, Debug)]
+pub struct Adaptive {
+	/// Largest allowed distance between the surface and the bilinear patch of a cell.
+	pub tolerance: f32,
+	/// Upper bound on the number of triangles, the base grid is never coarsened to meet it.
+	pub max_triangles: usize,
+	/// Number of times a cell of the base grid may be halved.
+	pub max_depth: u32,
+}
+
+impl Default for Adaptive {
+	fn default() -> Self {
+		Self {
+			tolerance: 0.01,
+			max_triangles: 50_000,
+			max_depth: 5,
+		}
+	}
+}
+
+/// A square cell of the lattice, `size` lattice steps wide.
+#[derive(Clone, Copy)]
+struct Cell {
+	i: u32,
+	j: u32,
+	size: u32,
+	error: f32,
+}
+
+impl PartialEq for Cell {
+	fn eq(&self, other: &Self) -> bool {
+		self.error.total_cmp(&other.error).is_eq()
+	}
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Cell {
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		self.error.total_cmp(&other.error)
+	}
+}
+
+/// Lazily evaluated samples over the finest lattice of the quadtree.
+struct Lattice<F> {
+	f: F,
+	origin: (f32, f32),
+	step: (f32, f32),
+	/// Number of lattice steps after which each parameter wraps around, if periodic.
+	period: (Option<u32>, Option<u32>),
+	indices: HashMap<(u32, u32), usize>,
+	samples: Vec<Sample>,
+}
+
+impl<F: FnMut(f32, f32) -> Sample> Lattice<F> {
+	fn wrap(&self, (i, j): (u32, u32)) -> (u32, u32) {
+		(
+			self.period.0.map_or(i, |period| i % period),
+			self.period.1.map_or(j, |period| j % period),
+		)
+	}
+
+	fn get(&mut self, i: u32, j: u32) -> &Sample {
+		let (i, j) = self.wrap((i, j));
+		let index = match self.indices.get(&(i, j)) {
+			Some(&index) => index,
+			None => {
+				let u = self.origin.0 + i as f32 * self.step.0;
+				let v = self.origin.1 + j as f32 * self.step.1;
+				self.samples.push((self.f)(u, v));
+				self.indices.insert((i, j), self.samples.len() - 1);
+				self.samples.len() - 1
+			}
+		};
+		&self.samples[index]
+	}
+
+	/// Measures how far the center and the edge midpoints of a cell are from the
+	/// bilinear interpolation of its corners.
+	fn error(&mut self, i: u32, j: u32, size: u32, tolerance: f32) -> f32 {
+		let half = size / 2;
+		let mut point = |di: u32, dj: u32| {
+			let sample = self.get(i + di, j + dj);
+			(!sample.hidden).then_some(sample.position)
+		};
+		let corners = [point(0, 0), point(0, size), point(size, size), point(size, 0)];
+		let midpoints = [
+			(point(0, half), [0, 1]),
+			(point(half, size), [1, 2]),
+			(point(size, half), [2, 3]),
+			(point(half, 0), [3, 0]),
+		];
+		let center = point(half, half);
+
+		let points = corners.iter().chain(midpoints.iter().map(|(p, _)| p)).chain([&center]);
+		let visible = points.filter(|p| p.is_some()).count();
+		if visible == 0 {
+			return 0.0;
+		}
+		if visible < 9 {
+			// Refine along the border of hidden regions, after the worst curved cells.
+			return 2.0 * tolerance;
+		}
+
+		let corners = corners.map(Option::unwrap);
+		let mut error = center
+			.unwrap()
+			.distance(corners.iter().sum::<Vec3>() / 4.0);
+		for (midpoint, [a, b]) in midpoints {
+			error = error.max(midpoint.unwrap().distance((corners[a] + corners[b]) / 2.0));
+		}
+		error
+	}
+}
+
+/// Tessellates the rectangle `[u_min, u_max] x [v_min, v_max]` starting from a
+/// `u_segments` by `v_segments` grid and halving the cells that deviate the most from
+/// their bilinear patch until they are within `tolerance` or the triangle budget is
+/// spent. Cells next to finer ones are fanned around their center so the mesh has no
+/// cracks, periodic parameters wrap around instead of sampling the seam twice.
+/// Triangles touching hidden samples are left out.
+pub fn tessellate(
+	(u_min, u_max, v_min, v_max): (f32, f32, f32, f32),
+	(u_segments, v_segments): (usize, usize),
+	(u_periodic, v_periodic): (bool, bool),
+	adaptive: Adaptive,
+	f: impl FnMut(f32, f32) -> Sample,
+) -> (Vec<Sample>, Vec<u32>) {
+	let base = 1u32 << adaptive.max_depth;
+	let mut lattice = Lattice {
+		f,
+		origin: (u_min, v_min),
+		step: (
+			(u_max - u_min) / (u_segments as u32 * base) as f32,
+			(v_max - v_min) / (v_segments as u32 * base) as f32,
+		),
+		period: (
+			u_periodic.then_some(u_segments as u32 * base),
+			v_periodic.then_some(v_segments as u32 * base),
+		),
+		indices: HashMap::new(),
+		samples: Vec::new(),
+	};
+
+	let mut max_leaves = adaptive.max_triangles / 2;
+	loop {
+		let leaves = refine(&mut lattice, (u_segments, v_segments), base, adaptive, max_leaves);
+		let (vertices, indices) = triangulate(&mut lattice, &leaves);
+		let triangles = indices.len() / 3;
+		let base_leaves = u_segments * v_segments;
+		if triangles <= adaptive.max_triangles || leaves.len() <= base_leaves {
+			let samples = vertices
+				.into_iter()
+				.map(|index| std::mem::replace(&mut lattice.samples[index], Sample::new(Vec3::ZERO)))
+				.collect();
+			return (samples, indices);
+		}
+		// Fanned cells cost more than two triangles, shrink the leaf budget accordingly.
+		max_leaves = (leaves.len() * adaptive.max_triangles / triangles).min(leaves.len() - 1);
+	}
+}
+
+fn refine<F: FnMut(f32, f32) -> Sample>(
+	lattice: &mut Lattice<F>,
+	(u_segments, v_segments): (usize, usize),
+	base: u32,
+	adaptive: Adaptive,
+	max_leaves: usize,
+) -> Vec<Cell> {
+	let mut leaves = Vec::new();
+	let mut queue = BinaryHeap::new();
+	let mut push = |lattice: &mut Lattice<F>, queue: &mut BinaryHeap<Cell>, i, j, size| {
+		let error = lattice.error(i, j, size, adaptive.tolerance);
+		let cell = Cell { i, j, size, error };
+		if size > 1 && error > adaptive.tolerance {
+			queue.push(cell);
+		} else {
+			leaves.push(cell);
+		}
+	};
+
+	for (i, j) in itertools::iproduct!(0..u_segments as u32, 0..v_segments as u32) {
+		push(lattice, &mut queue, i * base, j * base, base);
+	}
+
+	let mut count = u_segments * v_segments;
+	while let Some(cell) = queue.peek() {
+		if count + 3 > max_leaves {
+			break;
+		}
+		let Cell { i, j, size, .. } = *cell;
+		queue.pop();
+		let half = size / 2;
+		count += 3;
+		for (di, dj) in [(0, 0), (0, half), (half, half), (half, 0)] {
+			push(lattice, &mut queue, i + di, j + dj, half);
+		}
+	}
+	leaves.extend(queue);
+	leaves
+}
+
+/// Returns the lattice points used as vertices, in order, and the triangles over them.
+fn triangulate<F: FnMut(f32, f32) -> Sample>(
+	lattice: &mut Lattice<F>,
+	leaves: &[Cell],
+) -> (Vec<usize>, Vec<u32>) {
+	let corners: HashSet<(u32, u32)> = leaves
+		.iter()
+		.flat_map(|&Cell { i, j, size, .. }| {
+			[(i, j), (i, j + size), (i + size, j + size), (i + size, j)]
+		})
+		.map(|key| lattice.wrap(key))
+		.collect();
+
+	let mut vertices = Vec::new();
+	let mut remap = HashMap::new();
+	let mut vertex = |lattice: &mut Lattice<F>, key: (u32, u32)| -> Option<u32> {
+		let key = lattice.wrap(key);
+		lattice.get(key.0, key.1);
+		let index = lattice.indices[&key];
+		if lattice.samples[index].hidden {
+			return None;
+		}
+		Some(*remap.entry(key).or_insert_with(|| {
+			vertices.push(index);
+			vertices.len() as u32 - 1
+		}))
+	};
+
+	let mut indices = Vec::new();
+	let mut boundary = Vec::new();
+	for &Cell { i, j, size, .. } in leaves {
+		// Walk the border in the same orientation as the uniform grid.
+		boundary.clear();
+		boundary.extend((0..size).map(|k| (i, j + k)));
+		boundary.extend((0..size).map(|k| (i + k, j + size)));
+		boundary.extend((0..size).map(|k| (i + size, j + size - k)));
+		boundary.extend((0..size).map(|k| (i + size - k, j)));
+		boundary.retain(|&key| corners.contains(&lattice.wrap(key)));
+
+		let ring: Vec<_> = boundary.iter().map(|&key| vertex(lattice, key)).collect();
+		if ring.len() == 4 {
+			for [a, b, c] in [[0, 1, 2], [2, 3, 0]] {
+				if let (Some(a), Some(b), Some(c)) = (ring[a], ring[b], ring[c]) {
+					indices.extend([a, b, c]);
+				}
+			}
+		} else if let Some(center) = vertex(lattice, (i + size / 2, j + size / 2)) {
+			for k in 0..ring.len() {
+				if let (Some(a), Some(b)) = (ring[k], ring[(k + 1) % ring.len()]) {
+					indices.extend([center, a, b]);
+				}
+			}
+		}
+	}
+
+	(vertices, indices)
+}
+
+/// Merges the vertices lying on the border of the mesh that are within `tolerance` of
+/// each other, so seams of closed surfaces that were sampled twice are stitched together.
+/// Triangles that collapse in the process are removed.
+pub fn weld(positions: &[Vec3], indices: &mut Vec<u32>, tolerance: f32) {
+	let mut edges = HashMap::new();
+	for face in indices.chunks_exact(3) {
+		for k in 0..3 {
+			let (a, b) = (face[k], face[(k + 1) % 3]);
+			*edges.entry((a.min(b), a.max(b))).or_insert(0u32) += 1;
+		}
+	}
+	let mut border: Vec<u32> = edges
+		.into_iter()
+		.filter(|&(_, count)| count == 1)
+		.flat_map(|((a, b), _)| [a, b])
+		.collect();
+	border.sort_unstable();
+	border.dedup();
+
+	let cell = |p: Vec3| (p / tolerance).floor().as_ivec3();
+	let mut grid: HashMap<_, Vec<u32>> = HashMap::new();
+	let mut remap: HashMap<u32, u32> = HashMap::new();
+	for index in border {
+		let position = positions[index as usize];
+		let key = cell(position);
+		let neighbour = itertools::iproduct!(-1..=1, -1..=1, -1..=1)
+			.filter_map(|(x, y, z)| grid.get(&(key + glam::ivec3(x, y, z))))
+			.flatten()
+			.find(|&&other| positions[other as usize].distance(position) <= tolerance);
+		match neighbour {
+			Some(&other) => {
+				remap.insert(index, other);
+			}
+			None => grid.entry(key).or_default().push(index),
+		}
+	}
+
+	if remap.is_empty() {
+		return;
+	}
+	for index in indices.iter_mut() {
+		*index = remap.get(index).copied().unwrap_or(*index);
+	}
+	let welded = indices
+		.chunks_exact(3)
+		.filter(|face| face[0] != face[1] && face[1] != face[2] && face[2] != face[0])
+		.flatten()
+		.copied()
+		.collect();
+	*indices = welded;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::engine::SurfaceConfig;
+	use std::f32::consts::{PI, TAU};
+
+	/// Number of triangles using each edge.
+	fn edges(indices: &[u32]) -> HashMap<(u32, u32), u32> {
+		let mut edges = HashMap::new();
+		for face in indices.chunks_exact(3) {
+			for k in 0..3 {
+				let (a, b) = (face[k], face[(k + 1) % 3]);
+				*edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+			}
+		}
+		edges
+	}
+
+	#[test]
+	fn adaptive_has_no_cracks() {
+		let bump = |u: f32, v: f32| Sample::new(Vec3::new(u, v, (-20.0 * (u * u + v * v)).exp()));
+		for max_triangles in [200, 5000] {
+			let adaptive = Adaptive {
+				tolerance: 1e-3,
+				max_triangles,
+				max_depth: 5,
+			};
+			let (samples, indices) = tessellate((-1.0, 1.0, -1.0, 1.0), (4, 4), (false, false), adaptive, bump);
+			let triangles = indices.len() / 3;
+			assert!(triangles > 4 * 4 * 2 && triangles <= max_triangles, "{triangles} triangles");
+
+			// Inside the square every edge has a triangle on both sides, a T-junction would
+			// leave one with a single triangle.
+			let side = |a: f32, b: f32| (a.abs() - 1.0).abs() < 1e-5 && (a - b).abs() < 1e-5;
+			for ((a, b), count) in edges(&indices) {
+				let (a, b) = (samples[a as usize].position, samples[b as usize].position);
+				let border = side(a.x, b.x) || side(a.y, b.y);
+				assert!(count == 2 || count == 1 && border, "edge {a} {b} has {count} triangles");
+			}
+		}
+	}
+
+	/// Whether every edge of the grid sampled over `[0, TAU] x [0, v_max]` is shared by
+	/// two triangles once welded with `tolerance`.
+	fn closed(f: impl Fn(f32, f32) -> Vec3, v_max: f32, tolerance: f32) -> bool {
+		let config = SurfaceConfig::new(0.0, TAU, 0.0, v_max, 24, 16).with_weld_tolerance(tolerance);
+		let (_, indices, _) = config.generate(|u, v| Sample::new(f(u, v)), |_, _| true);
+		edges(&indices).values().all(|&count| count == 2)
+	}
+
+	#[test]
+	fn weld_closes_seams() {
+		let torus = |u: f32, v: f32| Vec3::new((2.0 + v.cos()) * u.cos(), (2.0 + v.cos()) * u.sin(), v.sin());
+		assert!(closed(torus, TAU, 1e-4));
+		assert!(!closed(torus, TAU, 0.0));
+
+		// Rows at the poles collapse to a point, their triangles are dropped.
+		let sphere = |u: f32, v: f32| Vec3::new(v.sin() * u.cos(), v.sin() * u.sin(), v.cos());
+		assert!(closed(sphere, PI, 1e-4));
+		assert!(!closed(sphere, PI, 0.0));
+	}
+}
@@ -0,0 +1,313 @@
+use super::Canvas;
+use glam::{vec2, Vec2};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextVertex {
+	pub position: [f32; 2],
+	pub uv: [f32; 2],
+	pub color: [f32; 4],
+}
+
+/// Where a character is in the atlas and how to place it, in pixels.
+#[derive(Clone, Copy, Default)]
+struct Glyph {
+	uv_min: Vec2,
+	uv_max: Vec2,
+	size: Vec2,
+	/// From the pen position on the baseline to the bottom left corner, y up.
+	offset: Vec2,
+	advance: f32,
+}
+
+const FONT: &[u8] = include_bytes!("../../assets/DejaVuSansMono.ttf");
+const SIZE: f32 = 15.0;
+const FIRST: char = ' ';
+const LAST: char = '~';
+
+/// Screen space text and rectangles drawn over the scene, from an atlas of the
+/// printable ASCII characters. Everything queued since the last `upload` is drawn.
+pub struct Text {
+	rp: wgpu::RenderPipeline,
+	sb: wgpu::Buffer,
+	group: wgpu::BindGroup,
+	vb: Option<wgpu::Buffer>,
+	vlen: u32,
+	glyphs: Vec<Glyph>,
+	/// Texture coordinates of a texel fully covered, for rectangles.
+	solid: Vec2,
+	ascent: f32,
+	line_height: f32,
+	vertices: Vec<TextVertex>,
+}
+
+impl Text {
+	pub fn new(canvas: &Canvas) -> Self {
+		let font = fontdue::Font::from_bytes(FONT, fontdue::FontSettings::default()).unwrap();
+		let line = font.horizontal_line_metrics(SIZE).unwrap();
+
+		// One row per sixteen characters, after a solid block for the rectangles.
+		let rasters: Vec<_> = (FIRST..=LAST).map(|c| font.rasterize(c, SIZE)).collect();
+		let cell_width = rasters.iter().map(|(m, _)| m.width).max().unwrap() + 2;
+		let cell_height = rasters.iter().map(|(m, _)| m.height).max().unwrap() + 2;
+		let (width, height) = (16 * cell_width, (rasters.len() / 16 + 2) * cell_height);
+		let mut pixels = vec![0u8; width * height];
+		for (x, y) in itertools::iproduct!(0..3, 0..3) {
+			pixels[x + y * width] = 255;
+		}
+
+		let texel = vec2(1.0 / width as f32, 1.0 / height as f32);
+		let mut glyphs = Vec::new();
+		for (k, (metrics, bitmap)) in rasters.iter().enumerate() {
+			let x0 = (k % 16) * cell_width + 1;
+			let y0 = (k / 16 + 1) * cell_height + 1;
+			for (row, line) in bitmap.chunks(metrics.width.max(1)).enumerate() {
+				let start = x0 + (y0 + row) * width;
+				pixels[start..start + line.len()].copy_from_slice(line);
+			}
+			let size = vec2(metrics.width as f32, metrics.height as f32);
+			let uv_min = vec2(x0 as f32, y0 as f32) * texel;
+			glyphs.push(Glyph {
+				uv_min,
+				uv_max: uv_min + size * texel,
+				size,
+				offset: vec2(metrics.xmin as f32, metrics.ymin as f32),
+				advance: metrics.advance_width,
+			});
+		}
+
+		let extent = wgpu::Extent3d {
+			width: width as u32,
+			height: height as u32,
+			depth_or_array_layers: 1,
+		};
+		let texture = canvas.device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("text atlas"),
+			size: extent,
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::R8Unorm,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+			view_formats: &[],
+		});
+		canvas.queue.write_texture(
+			texture.as_image_copy(),
+			&pixels,
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: std::num::NonZeroU32::new(width as u32),
+				rows_per_image: None,
+			},
+			extent,
+		);
+		let view = texture.create_view(&Default::default());
+		// Glyphs are drawn at their size on whole pixels.
+		let sampler = canvas.device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("text sampler"),
+			..Default::default()
+		});
+
+		let sb = canvas
+			.device
+			.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("text screen_buffer"),
+				usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+				contents: bytemuck::cast_slice(&[0.0f32; 4]),
+			});
+
+		let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+			binding,
+			visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+			ty,
+			count: None,
+		};
+		let layout = canvas
+			.device
+			.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+				label: Some("text layout"),
+				entries: &[
+					entry(
+						0,
+						wgpu::BindingType::Buffer {
+							ty: wgpu::BufferBindingType::Uniform,
+							has_dynamic_offset: false,
+							min_binding_size: None,
+						},
+					),
+					entry(
+						1,
+						wgpu::BindingType::Texture {
+							sample_type: wgpu::TextureSampleType::Float { filterable: true },
+							view_dimension: wgpu::TextureViewDimension::D2,
+							multisampled: false,
+						},
+					),
+					entry(2, wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)),
+				],
+			});
+		let group = canvas.device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("text group"),
+			layout: &layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: sb.as_entire_binding(),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::TextureView(&view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: wgpu::BindingResource::Sampler(&sampler),
+				},
+			],
+		});
+
+		let pipeline_layout =
+			canvas
+				.device
+				.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+					label: Some("text pipeline_layout"),
+					bind_group_layouts: &[&layout],
+					push_constant_ranges: &[],
+				});
+
+		let shader = canvas
+			.device
+			.create_shader_module(wgpu::include_wgsl!("text.wgsl"));
+
+		let rp = canvas
+			.device
+			.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+				label: Some("text render_pipeline"),
+				layout: Some(&pipeline_layout),
+				vertex: wgpu::VertexState {
+					module: &shader,
+					entry_point: "vs_main",
+					buffers: &[wgpu::VertexBufferLayout {
+						step_mode: wgpu::VertexStepMode::Vertex,
+						array_stride: std::mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+						attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
+					}],
+				},
+				fragment: Some(wgpu::FragmentState {
+					module: &shader,
+					entry_point: "fs_main",
+					targets: &[Some(wgpu::ColorTargetState {
+						format: canvas.config.format,
+						blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+						write_mask: wgpu::ColorWrites::ALL,
+					})],
+				}),
+				// Over everything else.
+				depth_stencil: Some(wgpu::DepthStencilState {
+					format: wgpu::TextureFormat::Depth32Float,
+					depth_write_enabled: false,
+					depth_compare: wgpu::CompareFunction::Always,
+					stencil: wgpu::StencilState::default(),
+					bias: wgpu::DepthBiasState::default(),
+				}),
+				primitive: wgpu::PrimitiveState::default(),
+				multisample: wgpu::MultisampleState::default(),
+				multiview: None,
+			});
+
+		Self {
+			rp,
+			sb,
+			group,
+			vb: None,
+			vlen: 0,
+			glyphs,
+			solid: texel * 1.5,
+			ascent: line.ascent,
+			line_height: line.new_line_size.ceil(),
+			vertices: Vec::new(),
+		}
+	}
+
+	fn glyph(&self, c: char) -> &Glyph {
+		let c = if (FIRST..=LAST).contains(&c) { c } else { '?' };
+		&self.glyphs[c as usize - FIRST as usize]
+	}
+
+	/// Width and height of `text` in pixels.
+	pub fn measure(&self, text: &str) -> Vec2 {
+		let width = text
+			.lines()
+			.map(|line| line.chars().map(|c| self.glyph(c).advance).sum::<f32>())
+			.fold(0.0, f32::max);
+		vec2(width, text.lines().count() as f32 * self.line_height)
+	}
+
+	fn quad(&mut self, min: Vec2, max: Vec2, uv_min: Vec2, uv_max: Vec2, color: [f32; 4]) {
+		let vertex = |x: f32, y: f32, u: f32, v: f32| TextVertex {
+			position: [x, y],
+			uv: [u, v],
+			color,
+		};
+		let (a, b) = (vertex(min.x, min.y, uv_min.x, uv_min.y), vertex(max.x, min.y, uv_max.x, uv_min.y));
+		let (c, d) = (vertex(max.x, max.y, uv_max.x, uv_max.y), vertex(min.x, max.y, uv_min.x, uv_max.y));
+		self.vertices.extend([a, b, c, c, d, a]);
+	}
+
+	/// Queues a filled rectangle, in pixels from the top left corner of the window.
+	pub fn rect(&mut self, min: Vec2, max: Vec2, color: [f32; 4]) {
+		self.quad(min, max, self.solid, self.solid, color);
+	}
+
+	/// Queues `text` with its top left corner at `at`, in pixels from the top left corner
+	/// of the window. Characters outside of printable ASCII show as `?`.
+	pub fn print(&mut self, at: Vec2, text: &str, color: [f32; 4]) {
+		for (row, line) in text.lines().enumerate() {
+			let baseline = (at.y + row as f32 * self.line_height + self.ascent).round();
+			let mut x = at.x.round();
+			for c in line.chars() {
+				let glyph = *self.glyph(c);
+				let min = vec2(x + glyph.offset.x, baseline - glyph.offset.y - glyph.size.y);
+				self.quad(min, min + glyph.size, glyph.uv_min, glyph.uv_max, color);
+				x += glyph.advance;
+			}
+		}
+	}
+
+	/// Queues `text` centered on `at`.
+	pub fn label(&mut self, at: Vec2, text: &str, color: [f32; 4]) {
+		self.print(at - self.measure(text) / 2.0, text, color);
+	}
+
+	/// Queues `text` over a translucent dark background, padded by `padding` pixels.
+	pub fn panel(&mut self, at: Vec2, text: &str, color: [f32; 4], padding: f32) {
+		let size = self.measure(text);
+		self.rect(at, at + size + 2.0 * padding, [0.0, 0.0, 0.0, 0.6]);
+		self.print(at + padding, text, color);
+	}
+
+	/// Sends what was queued to the GPU and empties the queue.
+	pub fn upload(&mut self, canvas: &Canvas) {
+		let screen = [canvas.config.width as f32, canvas.config.height as f32, 0.0, 0.0];
+		canvas.queue.write_buffer(&self.sb, 0, bytemuck::cast_slice(&screen));
+		self.vlen = self.vertices.len() as u32;
+		self.vb = (!self.vertices.is_empty()).then(|| {
+			canvas
+				.device
+				.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+					label: Some("text vertex_buffer"),
+					usage: wgpu::BufferUsages::VERTEX,
+					contents: bytemuck::cast_slice(&self.vertices),
+				})
+		});
+		self.vertices.clear();
+	}
+
+	pub fn render<'r>(&'r self, render_pass: &mut wgpu::RenderPass<'r>) {
+		let Some(vb) = &self.vb else { return };
+		render_pass.set_bind_group(0, &self.group, &[]);
+		render_pass.set_pipeline(&self.rp);
+		render_pass.set_vertex_buffer(0, vb.slice(..));
+		render_pass.draw(0..self.vlen, 0..1);
+	}
+}
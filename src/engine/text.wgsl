@@ -0,0 +1,33 @@
+struct VertexInput {
+	@location(0) position: vec2<f32>,
+	@location(1) uv: vec2<f32>,
+	@location(2) color: vec4<f32>,
+}
+
+struct FragmentInput {
+	@builtin(position) position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+	@location(1) color: vec4<f32>,
+}
+
+// Width and height of the window in pixels.
+@group(0) @binding(0) var<uniform> screen: vec4<f32>;
+@group(0) @binding(1) var atlas: texture_2d<f32>;
+@group(0) @binding(2) var atlas_sampler: sampler;
+
+@vertex
+fn vs_main(input: VertexInput) -> FragmentInput {
+	var output: FragmentInput;
+	// Pixels from the top left corner to normalized device coordinates.
+	let ndc = input.position / screen.xy * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+	output.position = vec4<f32>(ndc, 0.0, 1.0);
+	output.uv = input.uv;
+	output.color = input.color;
+	return output;
+}
+
+@fragment
+fn fs_main(input: FragmentInput) -> @location(0) vec4<f32> {
+	let a = input.color.a * textureSample(atlas, atlas_sampler, input.uv).r;
+	return vec4<f32>(input.color.rgb * a, a);
+}
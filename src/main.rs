@@ -1,57 +1,404 @@
 use glam::*;
-use std::{f32::consts::FRAC_PI_2, time::Instant};
+use std::{f32::consts::FRAC_PI_2, path::PathBuf, process::ExitCode, time::Instant};
 use winit::{
 	event::{
-		DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta,
-		VirtualKeyCode, WindowEvent,
+		DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton,
+		MouseScrollDelta, VirtualKeyCode, WindowEvent,
 	},
 	event_loop::EventLoop,
 	window::Window,
 };
 
+const HELP: &str = "\
+left drag      orbit the camera
+wheel          zoom
+right click    pick points, two give a shortest path
+space          play or pause the rotation and animation
+1 2 3          rotate around x, y or z
+r              reload the script
+s, shift s     save a picture, smoother with shift
+e              save the lines of the surface as SVG
+c              cycle the shading
+n              tangent frames
+f              cycle the vector field display
+l              contours
+i              mesh statistics
+a g b          axes, ground grid, bounding box
+t              axes with labeled ticks around the surface
+p              show the plane
+x              intersection curves
+v              clip with the plane
+arrows         move and turn the plane
+page up/down   tilt the plane
+shift drag     rotate the plane
+ctrl drag      move the plane
+tab            hide the overlay
+?              this help";
+
+mod cli;
 mod engine;
 use engine::*;
 
 struct State {
+	path: PathBuf,
+	other_path: Option<PathBuf>,
 	clock: Instant,
 	canvas: Canvas,
 	camera: Camera,
 	surface: Surface,
+	/// A second surface to intersect with the first, it follows its rotation.
+	other: Option<Surface>,
+	/// Meshes loaded from files, in the space the scripts' `matrix()` map to. They follow
+	/// the rotation of the surface too.
+	overlays: Vec<(PathBuf, Mesh)>,
 	quad: Quad,
+	reference: Reference,
+	plane: Plane,
+	/// Whether the surfaces are cut by the plane.
+	clipping: bool,
+	show_intersections: bool,
+	/// Curves of the intersection of both surfaces, in the coordinates of the first one.
+	surface_curves: Vec<Vec<Vec3>>,
+	/// Plane in the coordinates of the surface and whether the intersections were shown
+	/// when the highlighted curves were computed, they only change when this does.
+	intersection_key: Option<(Option<Plane>, bool)>,
 	camera_transform: (f32, f32, f32),
 	pressed: bool,
+	modifiers: ModifiersState,
+	cursor: Vec2,
 	mesh_delta: Vec3,
-	quad_elapsed: f32,
 	play: bool,
 	show: bool,
+	text: Text,
+	show_hud: bool,
+	show_help: bool,
+	show_stats: bool,
+	/// Measurements of the surface, computed when first shown after it changed.
+	stats: Option<Stats>,
+	/// Error from the last reload or key press, if it failed.
+	error: Option<String>,
+	/// Seconds the animation has been playing.
+	time: f32,
+	/// Frames per second, smoothed.
+	fps: f32,
 }
 
 impl State {
-	fn new(event_loop: &EventLoop<()>) -> Self {
+	fn new(event_loop: &EventLoop<()>, path: PathBuf, other_path: Option<PathBuf>, overlay_paths: &[PathBuf]) -> Self {
 		let window = Window::new(event_loop).unwrap();
 		window.set_inner_size(winit::dpi::PhysicalSize::new(500., 500.));
-		let canvas = Canvas::new(window);
+		Self::with_canvas(Canvas::new(window), path, other_path, overlay_paths)
+	}
+
+	fn with_canvas(canvas: Canvas, path: PathBuf, other_path: Option<PathBuf>, overlay_paths: &[PathBuf]) -> Self {
 		let camera = Camera::new(&canvas);
 
 		let surface = Surface::new(
 			&canvas,
 			&camera,
-			"assets/default.rhai"
+			&path,
 		);
+		let other = other_path.as_ref().map(|path| Surface::new(&canvas, &camera, path));
+		let mut error = None;
+		let mut overlays = Vec::new();
+		for path in overlay_paths {
+			match Mesh::load(&canvas, &camera, path) {
+				Ok(mesh) => overlays.push((path.clone(), mesh)),
+				Err(e) => {
+					eprintln!("{}: {e}", path.display());
+					error.get_or_insert(format!("{}: {e}", path.display()));
+				}
+			}
+		}
 		let quad = Quad::new(&canvas, &camera);
+		let text = Text::new(&canvas);
 
-		Self {
+		let mut state = Self {
+			path,
+			other_path,
 			clock: Instant::now(),
 			canvas,
 			camera,
 			surface,
+			other,
+			overlays,
 			quad,
+			reference: Reference::default(),
+			plane: Plane::default(),
+			clipping: false,
+			show_intersections: false,
+			surface_curves: Vec::new(),
+			intersection_key: None,
 			camera_transform: (1.0, 0.0, 5.),
 			pressed: false,
+			modifiers: ModifiersState::empty(),
+			cursor: Vec2::ZERO,
 			mesh_delta: Vec3::Z,
-			quad_elapsed: 0.0,
 			play: false,
 			show: true,
+			text,
+			show_hud: true,
+			show_help: false,
+			show_stats: false,
+			stats: None,
+			error,
+			time: 0.0,
+			fps: 0.0,
+		};
+		state.intersect_surfaces();
+		state
+	}
+
+	/// Intersects both surfaces, their relative position never changes.
+	fn intersect_surfaces(&mut self) {
+		self.surface_curves = match &self.other {
+			Some(other) => {
+				let relative = self.surface.geometry.transform.inverse() * other.geometry.transform;
+				self.surface.geometry.intersection(&other.geometry, relative)
+			}
+			None => Vec::new(),
+		};
+		self.intersection_key = None;
+	}
+
+	/// Reloads both scripts and the overlaid meshes, keeping those that fail as they were.
+	fn reload(&mut self) {
+		self.error = None;
+		if let Err(e) = self.surface.update(&self.canvas, &self.camera, &self.path) {
+			self.error = Some(format!("{}: {e}", self.path.display()));
+		}
+		if let (Some(other), Some(path)) = (&mut self.other, &self.other_path) {
+			if let Err(e) = other.update(&self.canvas, &self.camera, path) {
+				self.error.get_or_insert(format!("{}: {e}", path.display()));
+			}
+		}
+		for (path, mesh) in &mut self.overlays {
+			match Mesh::load(&self.canvas, &self.camera, path) {
+				Ok(loaded) => *mesh = loaded,
+				Err(e) => {
+					self.error.get_or_insert(format!("{}: {e}", path.display()));
+				}
+			}
+		}
+		if let Some(error) = &self.error {
+			eprintln!("{error}");
+		}
+		self.intersect_surfaces();
+		self.stats = None;
+		self.time = 0.0;
+	}
+
+	/// Evaluates both scripts again at `time`, keeping the surfaces as they were if
+	/// either fails.
+	fn set_time(&mut self, time: f32) -> Result<(), String> {
+		self.time = time;
+		let result = self
+			.surface
+			.set_time(&self.canvas, &self.camera, time)
+			.map_err(|e| format!("{}: {e}", self.path.display()));
+		let other = match (&mut self.other, &self.other_path) {
+			(Some(other), Some(path)) => other
+				.set_time(&self.canvas, &self.camera, time)
+				.map_err(|e| format!("{}: {e}", path.display())),
+			_ => Ok(()),
+		};
+		self.intersect_surfaces();
+		self.stats = None;
+		result.and(other)
+	}
+
+	/// Moves the second surface along with the first and updates what depends on where
+	/// they and the camera are.
+	fn sync(&mut self) {
+		if let Some(other) = &mut self.other {
+			let relative = self.surface.geometry.transform.inverse() * other.geometry.transform;
+			other.mesh.transform = self.surface.mesh.transform * relative;
+		}
+		for (_, mesh) in &mut self.overlays {
+			mesh.transform = self.surface.mesh.transform * self.surface.geometry.transform.inverse();
+		}
+
+		self.reference.update(&self.canvas, &self.camera, self.camera_transform.2);
+
+		// The section of the clipped surface is outlined even when intersections are hidden.
+		let plane = (self.clipping || self.show_intersections && self.show).then(|| self.plane.local(self.surface.mesh.transform));
+		let key = (plane, self.show_intersections);
+		if self.intersection_key != Some(key) {
+			self.intersection_key = Some(key);
+			let mut curves = Vec::new();
+			if self.show_intersections {
+				curves.extend(self.surface_curves.iter().cloned());
+			}
+			if let Some(plane) = plane {
+				curves.extend(self.surface.geometry.plane_intersection(plane));
+			}
+			self.surface.set_intersections(&self.canvas, &self.camera, &curves);
+		}
+	}
+
+	/// Sends what changed since the last frame to the GPU, with the overlay if `hud`.
+	fn prepare(&mut self, hud: bool) {
+		self.camera.set_transform(
+			&self.canvas.queue,
+			self.camera_transform.0,
+			self.camera_transform.1,
+			self.camera_transform.2,
+		);
+		let size = vec2(self.canvas.config.width as f32, self.canvas.config.height as f32);
+		self.surface.annotate(&mut self.text, &self.camera, size);
+		if hud {
+			self.hud();
+		}
+		self.text.upload(&self.canvas);
+		let clip = self.clipping.then_some(self.plane);
+		self.surface.update_transform_buffer(&self.canvas.queue);
+		self.surface.mesh.set_clip(&self.canvas.queue, clip);
+		if let Some(other) = &mut self.other {
+			other.update_transform_buffer(&self.canvas.queue);
+			other.mesh.set_clip(&self.canvas.queue, clip);
+		}
+		for (_, mesh) in &self.overlays {
+			mesh.update_transform_buffer(&self.canvas.queue);
+			mesh.set_clip(&self.canvas.queue, clip);
+		}
+		self.quad.set_transform(
+			&self.canvas.queue,
+			self.plane.transform(self.surface.geometry.size() / 2.0),
+		);
+	}
+
+	/// Draws everything into `view`, with the depth of the fragments in `depth_view`.
+	fn draw(&self, view: &wgpu::TextureView, depth_view: &wgpu::TextureView) {
+		let mut encoder = self
+			.canvas
+			.device
+			.create_command_encoder(&Default::default());
+
+		{
+			let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+				label: Some("render_pass"),
+				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+					view,
+					resolve_target: None,
+					ops: wgpu::Operations {
+						load: wgpu::LoadOp::Clear(wgpu::Color {
+							r: 0.3,
+							g: 0.3,
+							b: 0.3,
+							a: 1.0,
+						}),
+						store: true,
+					},
+				})],
+				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+					view: depth_view,
+					depth_ops: Some(wgpu::Operations {
+						load: wgpu::LoadOp::Clear(1.0),
+						store: true,
+					}),
+					stencil_ops: None,
+				}),
+			});
+			if self.show {
+				self.quad.render(&mut rp, &self.camera);
+			}
+			self.reference.render(&mut rp, &self.camera);
+			self.surface.render(&mut rp, &self.camera);
+			if let Some(other) = &self.other {
+				other.render(&mut rp, &self.camera);
+			}
+			for (_, mesh) in &self.overlays {
+				mesh.render(&mut rp, &self.camera);
+			}
+			self.text.render(&mut rp);
+		}
+		self.canvas.queue.submit(Some(encoder.finish()));
+	}
+
+	/// The view without the overlay as RGBA rows, rendered at `factor` times the size of
+	/// the window and scaled back down to smooth the edges.
+	fn capture(&mut self, factor: u32) -> Vec<u8> {
+		let (width, height) = (self.canvas.config.width, self.canvas.config.height);
+		let largest = self.canvas.device.limits().max_texture_dimension_2d;
+		let factor = factor.min(largest / width.max(height)).max(1);
+		let target = Target::new(&self.canvas, width * factor, height * factor);
+		self.prepare(false);
+		self.draw(&target.view, &target.depth_view);
+		downsample(&target.read(&self.canvas), target.width, target.height, factor)
+	}
+
+	/// Saves `capture(factor)` to a PNG named after the time.
+	fn screenshot(&mut self, factor: u32) {
+		let (width, height) = (self.canvas.config.width, self.canvas.config.height);
+		let pixels = self.capture(factor);
+		let path = stamped("png");
+		match write_png(&path, width, height, &pixels) {
+			Ok(()) => println!("saved {path}"),
+			Err(e) => self.error = Some(format!("{path}: {e}")),
+		}
+	}
+
+	/// Saves the parameter lines, silhouette and contours of the surface as seen now to an
+	/// SVG named after the time.
+	fn save_drawing(&mut self) {
+		let size = (self.canvas.config.width, self.canvas.config.height);
+		let path = stamped("svg");
+		let result = std::fs::File::create(&path)
+			.and_then(|file| self.surface.drawing(&self.camera, size).write_svg(&mut std::io::BufWriter::new(file)));
+		match result {
+			Ok(()) => println!("saved {path}"),
+			Err(e) => self.error = Some(format!("{path}: {e}")),
+		}
+	}
+
+	/// Queues the status, the error of the last reload and the help.
+	fn hud(&mut self) {
+		let geometry = &self.surface.geometry;
+		let (yaw, pitch, distance) = self.camera_transform;
+		let status = format!(
+			"{}\n{} vertices, {} triangles{}\nshading {:?}{}, field {:?}\ncamera yaw {yaw:.2} pitch {pitch:.2} distance {distance:.2}\ntime {:.2}s{}\n{:.0} fps, ? for help",
+			self.path.display(),
+			geometry.vertices.len(),
+			geometry.indices.len() / 3,
+			match geometry.bad_samples {
+				0 => String::new(),
+				n => format!(", {n} samples NaN or infinite"),
+			},
+			self.surface.shading,
+			self.surface.shading_range.map_or(String::new(), |range| format!(" from {:.3} to {range:.3}", -range)),
+			self.surface.field_mode,
+			self.time,
+			if self.play { "" } else { " (paused)" },
+			self.fps,
+		);
+		let mut status = status;
+		for pick in self.surface.picks() {
+			let (uv, p) = (pick.uv, pick.position);
+			status += &format!("\npicked ({:.3}, {:.3}) at ({:.3}, {:.3}, {:.3})", uv.x, uv.y, p.x, p.y, p.z);
+		}
+		if self.surface.picks().len() == 2 {
+			status += &match self.surface.path_length() {
+				Some(length) => format!("\nshortest path along the mesh {length:.4}"),
+				None => "\nthe picked points are not connected".into(),
+			};
+		}
+		if self.show_stats {
+			let geometry = &self.surface.geometry;
+			let stats = self
+				.stats
+				.get_or_insert_with(|| Stats::new(geometry));
+			status += &format!("\n{stats}");
+		}
+		let (margin, padding) = (8.0, 6.0);
+		self.text.panel(Vec2::splat(margin), &status, [1.0; 4], padding);
+		if let Some(error) = &self.error {
+			let y = 2.0 * margin + 2.0 * padding + self.text.measure(&status).y;
+			self.text.panel(vec2(margin, y), error, [1.0, 0.4, 0.4, 1.0], padding);
+		}
+		if self.show_help {
+			let width = self.canvas.config.width as f32;
+			let x = width - margin - 2.0 * padding - self.text.measure(HELP).x;
+			self.text.panel(vec2(x, margin), HELP, [1.0; 4], padding);
 		}
 	}
 }
@@ -61,6 +408,21 @@ impl engine::EventHandler for State {
 		match event {
 			Event::WindowEvent { event, .. } => match event {
 				WindowEvent::Resized(_) => self.camera.resize(&self.canvas),
+				WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+				WindowEvent::ReceivedCharacter('?') => self.show_help = !self.show_help,
+				WindowEvent::CursorMoved { position, .. } => {
+					self.cursor = vec2(position.x as f32, position.y as f32);
+				}
+				WindowEvent::MouseInput {
+					state: ElementState::Released,
+					button: MouseButton::Right,
+					..
+				} => {
+					let size = vec2(self.canvas.config.width as f32, self.canvas.config.height as f32);
+					let ndc = vec2(2.0, -2.0) * self.cursor / size + vec2(-1.0, 1.0);
+					let ray = self.camera.ray(ndc);
+					self.surface.pick(&self.canvas, &self.camera, ray);
+				}
 				WindowEvent::MouseInput { state, button, .. } => {
 					self.pressed = *button == MouseButton::Left && *state == ElementState::Pressed;
 				}
@@ -88,97 +450,95 @@ impl engine::EventHandler for State {
 					VirtualKeyCode::Key1 => self.mesh_delta = Vec3::X,
 					VirtualKeyCode::Key2 => self.mesh_delta = Vec3::Y,
 					VirtualKeyCode::Key3 => self.mesh_delta = Vec3::Z,
-					//VirtualKeyCode::T => self.surface.mesh.transform = Mat4::IDENTITY,
-					VirtualKeyCode::R => {
-						self.surface.update(&self.canvas, &self.camera, "assets/default.rhai");
-						self.quad_elapsed = 0.0;
-					},
+					VirtualKeyCode::C => {
+						let shading = self.surface.shading.next();
+						self.surface.set_shading(&self.canvas.queue, shading);
+					}
+					VirtualKeyCode::N => self.surface.toggle_frames(&self.canvas, &self.camera),
+					VirtualKeyCode::F => {
+						let mode = self.surface.field_mode.next();
+						if let Err(e) = self.surface.set_field_mode(&self.canvas, &self.camera, mode) {
+							self.error = Some(e.into());
+						}
+					}
+					VirtualKeyCode::L => self.surface.toggle_contours(&self.canvas, &self.camera),
+					VirtualKeyCode::A => self.reference.show_axes = !self.reference.show_axes,
+					VirtualKeyCode::G => self.reference.show_grid = !self.reference.show_grid,
+					VirtualKeyCode::B => self.surface.toggle_bbox(&self.canvas, &self.camera),
+					VirtualKeyCode::T => self.surface.toggle_rulers(&self.canvas, &self.camera),
+					VirtualKeyCode::X => self.show_intersections = !self.show_intersections,
+					VirtualKeyCode::V => self.clipping = !self.clipping,
+					VirtualKeyCode::Up => self.plane.offset += 0.1,
+					VirtualKeyCode::Down => self.plane.offset -= 0.1,
+					VirtualKeyCode::Left => self.plane.rotate(0.1, 0.0),
+					VirtualKeyCode::Right => self.plane.rotate(-0.1, 0.0),
+					VirtualKeyCode::PageUp => self.plane.rotate(0.0, 0.1),
+					VirtualKeyCode::PageDown => self.plane.rotate(0.0, -0.1),
+					VirtualKeyCode::I => self.show_stats = !self.show_stats,
+					VirtualKeyCode::Tab => self.show_hud = !self.show_hud,
+					VirtualKeyCode::R => self.reload(),
+					VirtualKeyCode::S => self.screenshot(if self.modifiers.shift() { 4 } else { 2 }),
+					VirtualKeyCode::E => self.save_drawing(),
 					_ => {}
 				},
 				_ => {}
 			},
-			Event::DeviceEvent { event, .. } => match event {
-				DeviceEvent::MouseMotion { delta } => {
-					if self.pressed {
-						let (yaw, pitch, _) = &mut self.camera_transform;
-
-						*yaw -= delta.0 as f32 / 100.;
-						*pitch += delta.1 as f32 / 100.;
-
-						if *pitch >= FRAC_PI_2 {
-							*pitch = FRAC_PI_2 - f32::EPSILON;
-						} else if *pitch < -FRAC_PI_2 {
-							*pitch = f32::EPSILON - FRAC_PI_2;
-						}
-					}
+			Event::DeviceEvent {
+				event: DeviceEvent::MouseMotion { delta },
+				..
+			} if self.pressed => {
+				let (dx, dy) = (delta.0 as f32 / 100., delta.1 as f32 / 100.);
+				// Shift drags rotate the plane, control drags move it along its normal.
+				if self.modifiers.shift() {
+					self.plane.rotate(dx, -dy);
+					return;
 				}
-				_ => {}
-			},
+				if self.modifiers.ctrl() {
+					self.plane.offset -= dy * self.surface.geometry.size() / 4.0;
+					return;
+				}
+				let (yaw, pitch, _) = &mut self.camera_transform;
+
+				*yaw -= dx;
+				*pitch += dy;
+
+				if *pitch >= FRAC_PI_2 {
+					*pitch = FRAC_PI_2 - f32::EPSILON;
+				} else if *pitch < -FRAC_PI_2 {
+					*pitch = f32::EPSILON - FRAC_PI_2;
+				}
+			}
 			_ => {}
 		}
 	}
 
 	fn update(&mut self) {
 		let dt = self.clock.elapsed().as_secs_f32();
+		if dt > 0.0 {
+			self.fps = if self.fps > 0.0 { 0.95 * self.fps + 0.05 / dt } else { 1.0 / dt };
+		}
 		if self.play {
+			self.time += dt;
 			self.surface.mesh.transform *= Mat4::from_quat(Quat::from_scaled_axis(self.mesh_delta*dt));
-			self.quad_elapsed += dt;
+			// Scripts that read `time` are evaluated again, keeping the rotation so far.
+			if self.surface.animated() || self.other.as_ref().is_some_and(Surface::animated) {
+				let rotation = self.surface.geometry.transform.inverse() * self.surface.mesh.transform;
+				if let Err(e) = self.set_time(self.time) {
+					self.error = Some(e);
+					self.play = false;
+				}
+				self.surface.mesh.transform = self.surface.geometry.transform * rotation;
+			}
 		}
-
+		self.sync();
 		self.clock = Instant::now();
 	}
 
 	fn render(&mut self) {
-		self.surface.mesh.update_transform_buffer(&self.canvas.queue);
-		self.quad.set_transform(
-			&self.canvas.queue,
-			Mat4::from_translation(Vec3::X * self.quad_elapsed.sin()),
-		);
-		self.camera.set_transform(
-			&self.canvas.queue,
-			self.camera_transform.0,
-			self.camera_transform.1,
-			self.camera_transform.2,
-		);
-		let frame = self.canvas.surface.get_current_texture().unwrap();
+		self.prepare(self.show_hud);
+		let frame = self.canvas.surface.as_ref().unwrap().get_current_texture().unwrap();
 		let view = frame.texture.create_view(&Default::default());
-		let mut encoder = self
-			.canvas
-			.device
-			.create_command_encoder(&Default::default());
-
-		{
-			let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-				label: Some("render_pass"),
-				color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-					view: &view,
-					resolve_target: None,
-					ops: wgpu::Operations {
-						load: wgpu::LoadOp::Clear(wgpu::Color {
-							r: 0.3,
-							g: 0.3,
-							b: 0.3,
-							a: 1.0,
-						}),
-						store: true,
-					},
-				})],
-				depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-					view: &self.canvas.depth_view,
-					depth_ops: Some(wgpu::Operations {
-						load: wgpu::LoadOp::Clear(1.0),
-						store: true,
-					}),
-					stencil_ops: None,
-				}),
-				..Default::default()
-			});
-			if self.show {
-				self.quad.render(&mut rp, &self.camera);
-			}
-			self.surface.mesh.render(&mut rp, &self.camera);
-		}
-		self.canvas.queue.submit(Some(encoder.finish()));
+		self.draw(&view, &self.canvas.depth_view);
 		frame.present();
 	}
 
@@ -187,8 +547,35 @@ impl engine::EventHandler for State {
 	}
 }
 
-fn main() {
-	let event_loop = EventLoop::new();
-	let state = State::new(&event_loop);
-	engine::run(event_loop, state);
+/// `surfaco-<milliseconds since 1970>.<extension>`, for files saved from the window.
+fn stamped(extension: &str) -> String {
+	let time = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap()
+		.as_millis();
+	format!("surfaco-{time}.{extension}")
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	match args.first().map(String::as_str) {
+		Some("stats") => cli::stats(&args[1..]),
+		Some("contours") => cli::contours(&args[1..]),
+		Some("intersect") => cli::intersect(&args[1..]),
+		Some("record") => cli::record(&args[1..]),
+		Some("export") => cli::export(&args[1..]),
+		Some("sample") => cli::sample(&args[1..]),
+		_ => {
+			let path = args.first().map_or("assets/default.rhai", String::as_str);
+			let (overlays, others): (Vec<PathBuf>, Vec<PathBuf>) =
+				args.iter().skip(1).map(PathBuf::from).partition(|path| is_mesh_path(path));
+			if others.len() > 1 {
+				eprintln!("only one other script can be shown, the rest must be .obj, .stl or .ply meshes");
+				return ExitCode::FAILURE;
+			}
+			let event_loop = EventLoop::new();
+			let state = State::new(&event_loop, path.into(), others.into_iter().next(), &overlays);
+			engine::run(event_loop, state);
+		}
+	}
 }